@@ -1,8 +1,41 @@
-use clap::{command, Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{db::DatabaseType, log::LogFormat, output::OutputFormat};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(after_help = "Exit codes: 0 success, 1 other errors, 2 invalid arguments, 3 config not found, 4 parse error, 5 connection failure, 6 query failure, 7 invalid key, 8 validation problems found, 9 history entry not found")]
 pub struct Cli {
+    /// The directory of the project files. Defaults to $DBML_HOME, then to the closest .dbml directory from the current one, then to $HOME/.dbml
+    #[arg(long, global = true)]
+    pub config_dir: Option<PathBuf>,
+
+    /// The connection profile (defined in each database's 'profiles') to be used instead of the default connection
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Only prints the results, warnings and errors
+    #[arg(long, short, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also prints the details, such as the time taken by each phase
+    #[arg(long, short, global = true)]
+    pub verbose: bool,
+
+    /// The format of the messages printed on stderr (the results of the commands are printed on stdout)
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Runs the command without writing any file, printing how the files would change as a unified diff instead
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// The format of the results printed on stdout
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub command: SubCommands,
 }
@@ -12,16 +45,31 @@ pub enum SubCommands {
 
     /// Scans the databases of a project to discover its schemas, tables, columns and relationships
     Scan {
-        /// The project to be scanned (there must be a file named {project}.yaml at $HOME/.dbml/)
+        /// The project to be scanned (there must be a file named {project}.yaml in the config directory)
         project: String,
+        /// Rescans every schema, even the ones that did not change since the last scan
+        #[arg(long)]
+        full: bool,
+        /// Only scans the given database (can be repeated)
+        #[arg(long = "database")]
+        databases: Vec<String>,
+        /// Only scans the given schema (can be repeated). Implies a rescan of the matching tables
+        #[arg(long = "schema")]
+        schemas: Vec<String>,
+        /// Only scans the tables whose whole name matches the given regex, such as 'user' or 'order_.*' (can be repeated). Implies a rescan of the matching tables
+        #[arg(long = "table")]
+        tables: Vec<String>,
     },
 
     /// Generates the .dbml file for a project
     Generate {
         /// The project to be have it's DBML file generated
         project: String,
-        /// The table from which the generation will start. If given, unrelated tables may not appear
+        /// The table from which the generation will start (format: {database_name}___{schema_name}___{table_name}, with names containing '.', '___' or '"' between double quotes, prefixed by {project}:: in workspaces). If given, unrelated tables may not appear
         starting_table: Option<String>,
+        /// Treats the name as a workspace ({name}.workspace.yaml in the config directory) and runs across all of its projects
+        #[arg(long, short)]
+        workspace: bool,
     },
 
     /// Searches for columns that match a given regex and offers an option to add them to the custom references if they are not referenced anywhere
@@ -30,24 +78,209 @@ pub enum SubCommands {
         project: String,
         /// The regex that will be used to search for the columns
         regex: String,
-        /// The key referenced by the columns that match regex (format: {database_name}___{table_name}.{column_name})
+        /// The key referenced by the columns that match regex (format: {database_name}___{schema_name}___{table_name}.{column_name}, with names containing '.', '___' or '"' between double quotes, prefixed by {project}:: in workspaces)
         referenced_key: Option<String>,
+        /// Treats the name as a workspace ({name}.workspace.yaml in the config directory) and runs across all of its projects
+        #[arg(long, short)]
+        workspace: bool,
     },
 
-    /// Performs a few validations in a project's config file (does not modify it)
+    /// Checks a project's config file against its JSON Schema and performs a few other validations (does not modify it)
     Validate {
         /// The project to be validated
         project: String,
+        /// Treats the name as a workspace ({name}.workspace.yaml in the config directory) and runs across all of its projects
+        #[arg(long, short)]
+        workspace: bool,
+    },
+
+    /// Lists the custom references whose key or referenced key no longer matches a scanned column, suggesting likely renamed columns
+    Stale {
+        /// The project to be checked
+        project: String,
+        /// Removes the stale custom references from the config file
+        #[arg(long)]
+        prune: bool,
+        /// Rewrites the stale custom references to their closest suggestion (when there is a single one). Combined with --prune, the ones that cannot be rewritten are removed
+        #[arg(long)]
+        rewrite: bool,
     },
 
-    /// Removes the scanned tables and references of every database in the project (does not change the custom references)
+    /// Removes the scanned tables and references of the databases in the project (does not change the custom references unless asked to)
     Clean {
         /// The project to be cleaned
         project: String,
+        /// Only cleans the given database (can be repeated)
+        #[arg(long = "database")]
+        databases: Vec<String>,
+        /// Only cleans the given schema (can be repeated)
+        #[arg(long = "schema")]
+        schemas: Vec<String>,
+        /// Also removes the custom references from or to the cleaned tables
+        #[arg(long)]
+        custom_references: bool,
+        /// Does not ask for confirmation before cleaning
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    /// Creates a project, asking for its databases and connection details (or taking them from the flags, with --non-interactive)
+    Init {
+        /// The project to be created (its file will be named {project}.yaml, in the config directory)
+        project: String,
+        /// Creates the project from the flags below instead of asking for its details
+        #[arg(long)]
+        non_interactive: bool,
+        /// The name of the project's database
+        #[arg(long, requires = "non_interactive", required_if_eq("non_interactive", "true"))]
+        database: Option<String>,
+        /// The type of the database
+        #[arg(long = "type", requires = "non_interactive", default_value = "mysql")]
+        r#type: DatabaseType,
+        /// The host of the database server
+        #[arg(long, requires = "non_interactive", default_value = "localhost")]
+        host: String,
+        /// The port of the database server
+        #[arg(long, requires = "non_interactive", default_value_t = 3306)]
+        port: u16,
+        /// The user to connect with
+        #[arg(long, requires = "non_interactive", required_if_eq("non_interactive", "true"))]
+        username: Option<String>,
+        /// The password to connect with (${VARIABLE} references are kept as they are, and expanded when the project is loaded)
+        #[arg(long, requires = "non_interactive", required_if_eq("non_interactive", "true"))]
+        password: Option<String>,
+        /// A schema to be ignored by the scans (can be repeated)
+        #[arg(long = "ignore-schema", requires = "non_interactive")]
+        ignore_schemas: Vec<String>,
+        /// Does not test the connection before creating the project
+        #[arg(long, requires = "non_interactive")]
+        skip_connection_test: bool,
+        /// Overwrites the project file if the project already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Lists, shows, copies, renames and deletes projects
+    Project {
+        #[command(subcommand)]
+        command: ProjectSubCommands,
+    },
+
+    /// Reads and edits the settings of a project's config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigSubCommands,
+    },
+
+    /// Lists the previous states of a project's files, recorded each time a command changed them
+    History {
+        /// The project whose history is listed
+        project: String,
+    },
+
+    /// Restores a project's files to their state before its last change (or before the given change), removing the undone changes from the history
+    Undo {
+        /// The project to be restored
+        project: String,
+        /// The id of the change to undo, with every change made after it (see 'dbml history')
+        #[arg(long)]
+        to: Option<u64>,
+        /// Does not ask for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    /// Upgrades a project's config file to the current version, keeping a backup of the original file (older files are otherwise only upgraded in memory until they are saved)
+    Migrate {
+        /// The project to be migrated
+        project: String,
+    },
+
+    /// Writes the JSON Schema of the project files, so editors can validate and autocomplete them
+    Schema {
+        /// Writes the JSON Schema of the scan store files instead
+        #[arg(long)]
+        store: bool,
+        /// The file to write the JSON Schema to. Defaults to dbml-project.schema.json (or dbml-store.schema.json) in the config directory
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    }
+
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectSubCommands {
+
+    /// Lists the projects in the config directory, with their databases and when they were last scanned
+    List,
+
+    /// Prints a summary of a project: its files, databases and references
+    Show {
+        /// The project to be shown
+        project: String,
+    },
+
+    /// Copies a project (with its scan results and DBML file) to a new project
+    Copy {
+        /// The project to be copied
+        project: String,
+        /// The name of the new project
+        new_project: String,
+    },
+
+    /// Renames a project (with its scan results and DBML file)
+    Rename {
+        /// The project to be renamed
+        project: String,
+        /// The new name of the project
+        new_project: String,
+    },
+
+    /// Deletes a project, with its scan results, DBML file and backups
+    Delete {
+        /// The project to be deleted
+        project: String,
+        /// Does not ask for confirmation before deleting
+        #[arg(long, short)]
+        yes: bool,
+    }
+
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubCommands {
+
+    /// Prints the value at a path of a project's config file
+    Get {
+        /// The project to be read
+        project: String,
+        /// The path of the value, with keys separated by dots (for example databases.pay.connection.port). Keys with dots are written between double quotes
+        path: String,
+    },
+
+    /// Sets the value at a path of a project's config file, checking it against the types of the config file
+    Set {
+        /// The project to be edited
+        project: String,
+        /// The path of the value, with keys separated by dots (for example databases.pay.connection.port). Keys with dots are written between double quotes
+        path: String,
+        /// The new value, in YAML (for example 3307, [sys, mysql] or {type: mysql, host: localhost})
+        value: String,
+        /// Adds the value to the list at the path instead of replacing it
+        #[arg(long)]
+        append: bool,
+    },
+
+    /// Removes the value at a path of a project's config file
+    Unset {
+        /// The project to be edited
+        project: String,
+        /// The path of the value, with keys separated by dots (for example databases.pay.configurations). Keys with dots are written between double quotes
+        path: String,
     }
 
 }
 
 pub fn parse() -> Cli {
-    return Cli::parse();
+    Cli::parse()
 }
\ No newline at end of file
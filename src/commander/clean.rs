@@ -1,35 +1,217 @@
 use anyhow::Result;
-use crate::config::{self, Config};
+use serde::Serialize;
+use crate::{config::{self, Config}, error::Error, key::{self, ColumnKey}, log, output::{self, Output}};
 
-use super::Command;
+use super::{confirm_changes, Command};
 
 pub struct CleanCommand {
     pub project: String,
+    pub databases: Vec<String>,
+    pub schemas: Vec<String>,
+    pub custom_references: bool,
+    pub yes: bool,
+}
+
+#[derive(Serialize)]
+struct CleanResult {
+    project: String,
+    /// Whether the config file was cleaned (it is not when there was nothing to remove, or when the removal was not confirmed)
+    cleaned: bool,
+    tables: Vec<String>,
+    references: Vec<String>,
+    custom_references: Vec<CustomReference>,
+}
+
+#[derive(Serialize)]
+struct CustomReference {
+    key: String,
+    referenced_key: String,
+}
+
+impl CleanResult {
+
+    fn is_empty(&self) -> bool {
+        self.tables.is_empty() && self.references.is_empty() && self.custom_references.is_empty()
+    }
+
+    fn get_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for table_key in &self.tables {
+            lines.push(format!("Table {}", table_key));
+        }
+        for key in &self.references {
+            lines.push(format!("References from {}", key));
+        }
+        for custom_reference in &self.custom_references {
+            lines.push(format!("Custom reference {} - {}", custom_reference.key, custom_reference.referenced_key));
+        }
+        lines
+    }
+
+}
+
+impl Output for CleanResult {
+
+    fn print_text(&self) {
+        if self.is_empty() {
+            println!("Nothing to clean");
+            return;
+        }
+        if !self.cleaned {
+            println!("Nothing was cleaned");
+            return;
+        }
+
+        println!("Removed from the config file of the '{}' project:", self.project);
+        for line in self.get_lines() {
+            println!("\t{}", line);
+        }
+    }
+
 }
 
 impl Command for CleanCommand {
 
     fn get_starting_message(&self) -> String {
-        format!("Validating the config file of the '{}' project", self.project)
+        format!("Cleaning the config file of the '{}' project", self.project)
     }
 
     async fn execute(&self) -> Result<()> {
         let mut config: Config = config::load(&self.project)?;
-        
-        if config.references.is_some() {
-            config.references = None;
+
+        for database_name in &self.databases {
+            if !config.databases.contains_key(database_name) {
+                return Err(Error::InvalidKey(format!("The database '{}' does not exist in the config file for project {}", database_name, self.project)).into());
+            }
+        }
+
+        let mut tables_to_remove: Vec<String> = Vec::new();
+        for (database_name, database) in &config.databases {
+            if let Some(tables) = &database.tables {
+                for table_name in tables.keys() {
+                    if self.contains_table(database_name, table_name) {
+                        tables_to_remove.push(key::format_table_key(database_name, table_name));
+                    }
+                }
+            }
+        }
+
+        let mut references_to_remove: Vec<String> = Vec::new();
+        if let Some(references) = &config.references {
+            for key in references.keys() {
+                if self.contains_column_key(key) {
+                    references_to_remove.push(key.to_string());
+                }
+            }
+        }
+
+        let mut custom_references_to_remove: Vec<(String, String)> = Vec::new();
+        if self.custom_references {
+            if let Some(custom_references) = &config.custom_references {
+                for (key, referenced_keys) in custom_references {
+                    for referenced_key in referenced_keys {
+                        if self.contains_column_key(key) || self.contains_column_key(referenced_key) {
+                            custom_references_to_remove.push((key.to_string(), referenced_key.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+
+        tables_to_remove.sort();
+        references_to_remove.sort();
+        custom_references_to_remove.sort();
+
+        let mut result = CleanResult {
+            project: self.project.to_string(),
+            cleaned: false,
+            tables: tables_to_remove,
+            references: references_to_remove,
+            custom_references: custom_references_to_remove.into_iter()
+                .map(|(key, referenced_key)| CustomReference { key, referenced_key })
+                .collect(),
+        };
+
+        if result.is_empty() {
+            return output::print(&result);
+        }
+
+        if !self.yes && !config::is_dry_run() {
+            let header = format!("The following will be removed from the config file of the '{}' project:", self.project);
+            if !confirm_changes(&header, result.get_lines())? {
+                return output::print(&result);
+            }
         }
 
         for (database_name, database) in &mut config.databases {
-            println!("Cleaning database {}", database_name);
-            
-            if database.tables.is_some() {
-                database.tables = None;
+            if !self.databases.is_empty() && !self.databases.contains(database_name) {
+                continue;
+            }
+
+            log::info(format!("Cleaning database {}", database_name));
+
+            if let Some(tables) = &mut database.tables {
+                tables.retain(|table_name, _| !self.contains_table(database_name, table_name));
+                if tables.is_empty() {
+                    database.tables = None;
+                }
+            }
+
+            // Forgets the fingerprints of the cleaned schemas, otherwise the next scan would consider them unchanged
+            if let Some(fingerprints) = &mut database.fingerprints {
+                fingerprints.retain(|schema_name, _| !self.schemas.is_empty() && !self.schemas.contains(schema_name));
+                if fingerprints.is_empty() {
+                    database.fingerprints = None;
+                }
+            }
+        }
+
+        if let Some(references) = &mut config.references {
+            references.retain(|key, _| !self.contains_column_key(key));
+            if references.is_empty() {
+                config.references = None;
+            }
+        }
+
+        if let Some(custom_references) = config.custom_references.as_mut().filter(|_| self.custom_references) {
+            for (key, referenced_keys) in custom_references.iter_mut() {
+                referenced_keys.retain(|referenced_key| !self.contains_column_key(key) && !self.contains_column_key(referenced_key));
+            }
+            custom_references.retain(|_, referenced_keys| !referenced_keys.is_empty());
+            if custom_references.is_empty() {
+                config.custom_references = None;
             }
         }
 
         config.save()?;
-        Ok(())
+
+        result.cleaned = true;
+        output::print(&result)
+    }
+
+}
+
+impl CleanCommand {
+
+    /// Whether a table of a database's scan store (`{schema_name}___{table_name}`) is targeted by the clean
+    fn contains_table(&self, database_name: &str, table_name: &str) -> bool {
+        match key::parse_store_name(table_name) {
+            Some((schema_name, _)) => self.contains_schema(database_name, &schema_name),
+            None => self.contains_schema(database_name, table_name),
+        }
+    }
+
+    /// Whether a column key (`{database_name}___{schema_name}___{table_name}.{column_name}`) is targeted by the clean
+    fn contains_column_key(&self, column_key: &str) -> bool {
+        match ColumnKey::parse(column_key) {
+            Ok(column_key) => self.contains_schema(&column_key.table.database_name, &column_key.table.schema_name),
+            Err(_) => false,
+        }
+    }
+
+    fn contains_schema(&self, database_name: &str, schema_name: &str) -> bool {
+        (self.databases.is_empty() || self.databases.iter().any(|database| database == database_name))
+        && (self.schemas.is_empty() || self.schemas.iter().any(|schema| schema == schema_name))
     }
 
-}
\ No newline at end of file
+}
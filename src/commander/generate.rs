@@ -1,47 +1,79 @@
-use std::collections::HashMap;
+use std::{collections::{BTreeMap, BTreeSet}, path::PathBuf};
 
-use anyhow::{anyhow, Result};
-use regex::Regex;
-use crate::{config::{self, Config, ProjectDatabaseTable}, dbml::{self, DBML}};
+use anyhow::Result;
+use serde::Serialize;
+use crate::{config::{self, Config, ProjectDatabaseTable}, dbml::{self, DBML}, error::Error, key::{ColumnKey, TableKey}, log, output::{self, Output}, workspace};
 
 use super::Command;
 
 pub struct GenerateCommand {
     pub project: String,
     pub starting_table: Option<String>,
+    pub workspace: bool,
 }
 
-struct TableInfo {
-    pub database_name: String,
-    pub table_name: String,
-    pub table: ProjectDatabaseTable,
+#[derive(Serialize)]
+struct GenerateResult {
+    path: PathBuf,
+    tables: usize,
+    references: usize,
 }
 
-impl Command for GenerateCommand {
+impl Output for GenerateResult {
 
-    fn get_starting_message(&self) -> String {
-        format!("Generating the DBML file for project '{}'", self.project)
+    fn print_text(&self) {
+        match config::is_dry_run() {
+            true => println!("DBML file with {} tables and {} references would be written to {}", self.tables, self.references, self.path.display()),
+            false => println!("DBML file with {} tables and {} references written to {}", self.tables, self.references, self.path.display()),
+        }
     }
 
-    async fn execute(&self) -> Result<()> {
-        let config: Config = config::load(&self.project)?;
-        let mut dbml: DBML = dbml::init(&self.project)?;
+}
 
-        if self.starting_table.is_some() {
-            return generate_from_starting_table(&config, &mut dbml, self.starting_table.as_ref().unwrap());
+impl Command for GenerateCommand {
+
+    fn get_starting_message(&self) -> String {
+        match self.workspace {
+            true => format!("Generating the DBML file for workspace '{}'", self.project),
+            false => format!("Generating the DBML file for project '{}'", self.project),
         }
+    }
 
-        generate_all(&config, &mut dbml)
+    async fn execute(&self) -> Result<()> {
+        let (config, mut dbml): (Config, DBML) = match self.workspace {
+            true => (workspace::load(&self.project)?.to_config(), dbml::init(&format!("{}.workspace", self.project))?),
+            false => (config::load(&self.project)?, dbml::init(&self.project)?),
+        };
+
+        let (tables, references) = match &self.starting_table {
+            Some(starting_table) => generate_from_starting_table(&config, &mut dbml, starting_table)?,
+            None => generate_all(&config, &mut dbml)?,
+        };
+
+        output::print(&GenerateResult {
+            path: dbml.get_path().to_path_buf(),
+            tables,
+            references,
+        })
     }
 
 }
 
-fn generate_all(config: &Config, dbml: &mut DBML) -> Result<()> {
+/// Writes every table and reference, returning how many of each were written
+fn generate_all(config: &Config, dbml: &mut DBML) -> Result<(usize, usize)> {
+    let mut table_count = 0;
+    let mut reference_count = 0;
 
     for (database_name, database) in &config.databases {
         if let Some(tables) = &database.tables {
             for (table_name, table) in tables {
-                dbml.write(table.to_dbml(database_name, table_name))?;
+                match TableKey::from_store(database_name, table_name) {
+                    Some(key) => {
+                        dbml.write(table.to_dbml(&key));
+                        table_count += 1;
+                    }
+                    None => log::warn(format!("Table {} of database {} is not named {{schema_name}}___{{table_name}}, skipping it", table_name, database_name)),
+                }
             }
         }
     }
@@ -51,9 +83,10 @@ fn generate_all(config: &Config, dbml: &mut DBML) -> Result<()> {
             for referenced_key in referenced_keys {
                 dbml.write(format!(
                     "Ref: {} - {}\n",
-                    key,
-                    referenced_key
-                ))?;
+                    dbml::format_key(key),
+                    dbml::format_key(referenced_key)
+                ));
+                reference_count += 1;
             }
         }
     }
@@ -63,69 +96,59 @@ fn generate_all(config: &Config, dbml: &mut DBML) -> Result<()> {
             for referenced_key in referenced_keys {
                 dbml.write(format!(
                     "Ref: {} - {}\n",
-                    key,
-                    referenced_key
-                ))?;
+                    dbml::format_key(key),
+                    dbml::format_key(referenced_key)
+                ));
+                reference_count += 1;
             }
         }
     }
 
     dbml.save()?;
-    Ok(())
+    Ok((table_count, reference_count))
 }
 
-fn generate_from_starting_table(config: &Config, dbml: &mut DBML, starting_table: &String) -> Result<()> {
-    let table_info = get_table(config, starting_table)?;
-    let dependencies = get_dependencies(config, starting_table);
-    let mut printed_tables: HashMap<String, ()> = HashMap::new();
+/// Writes the starting table and the tables it depends on, returning how many tables and references were written
+fn generate_from_starting_table(config: &Config, dbml: &mut DBML, starting_table: &str) -> Result<(usize, usize)> {
+    let table_key = TableKey::parse(starting_table)?;
+    let table = get_table(config, &table_key)?;
+    let dependencies = get_dependencies(config, &table_key);
+    let mut printed_tables: BTreeSet<TableKey> = BTreeSet::new();
+    let mut reference_count = 0;
 
-    dbml.write(table_info.table.to_dbml(&table_info.database_name, &table_info.table_name))?;
-    printed_tables.insert(starting_table.to_string(), ());
+    dbml.write(table.to_dbml(&table_key));
+    printed_tables.insert(table_key);
     for (dependency_table_key, (dependent_table_referencing_column, dependency_table_referenced_column)) in dependencies {
-        if !printed_tables.contains_key(&dependency_table_key) {
+        if !printed_tables.contains(&dependency_table_key) {
             let dependency_table = get_table(config, &dependency_table_key)?;
-            dbml.write(dependency_table.table.to_dbml(&dependency_table.database_name, &dependency_table.table_name))?;
+            dbml.write(dependency_table.to_dbml(&dependency_table_key));
+            printed_tables.insert(dependency_table_key);
         }
         dbml.write(format!(
             "Ref: {} - {}\n\n",
-            dependent_table_referencing_column,
-            dependency_table_referenced_column
-        ))?;
+            dbml::format_key(&dependent_table_referencing_column),
+            dbml::format_key(&dependency_table_referenced_column)
+        ));
+        reference_count += 1;
     }
 
-    Ok(())
+    dbml.save()?;
+    Ok((printed_tables.len(), reference_count))
 }
 
-fn get_table(config: &Config, table_key: &String) -> Result<TableInfo> {
-    let table_key_regex = Regex::new(r"^(.+)___(.+)$").unwrap();
-    if !table_key_regex.is_match(&table_key) {
-        return Err(anyhow!("The starting table must follow this pattern: DATABASE_NAME___TABLE_NAME"));
-    }
-
-    let parts: Vec<&str> = table_key.split("___").collect();
-    let database_name = parts[0];
-    let table_name = parts[1..].join("___");
-
-    if !config.databases.contains_key(database_name)
-    || !config.databases.get(database_name).unwrap().tables.clone().unwrap().contains_key(&table_name) {
-        return Err(anyhow!("The given starting table '{}' does not match an existing table from the config file for project {}", table_key, config.project));
-    }
-
-    Ok(TableInfo {
-        database_name: database_name.to_string(),
-        table_name: table_name.to_string(),
-        table: config.databases.get(database_name).unwrap().tables.clone().unwrap().get(&table_name).unwrap().clone()
-    })
+fn get_table<'a>(config: &'a Config, table_key: &TableKey) -> Result<&'a ProjectDatabaseTable> {
+    Ok(config.get_table(table_key)
+        .ok_or(Error::InvalidKey(format!("The given starting table '{}' does not match an existing table from the config file for project {}", table_key, config.project)))?)
 }
 
-fn get_dependencies(config: &Config, table_key: &String) -> HashMap<String, (String, String)> {
-    let mut dependencies: HashMap<String, (String, String)> = HashMap::new();
+fn get_dependencies(config: &Config, table_key: &TableKey) -> BTreeMap<TableKey, (String, String)> {
+    let mut dependencies: BTreeMap<TableKey, (String, String)> = BTreeMap::new();
     if let Some(config_references) = &config.references {
         for (reference_key, references) in config_references {
-            if reference_key.starts_with(format!("{}.", table_key).as_str()) {
+            if ColumnKey::parse(reference_key).is_ok_and(|reference_key| reference_key.table == *table_key) {
                 for reference in references {
-                    if let Some((table_key, _)) = reference.split_once('.') {
-                        dependencies.insert(table_key.to_string(), (reference_key.to_string(), reference.to_string()));
+                    if let Ok(referenced_key) = ColumnKey::parse(reference) {
+                        dependencies.insert(referenced_key.table, (reference_key.to_string(), reference.to_string()));
                     }
                 }
             }
@@ -133,4 +156,4 @@ fn get_dependencies(config: &Config, table_key: &String) -> HashMap<String, (Str
     }
 
     dependencies
-}
\ No newline at end of file
+}
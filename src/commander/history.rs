@@ -0,0 +1,234 @@
+use anyhow::Result;
+use serde::Serialize;
+use crate::{config, error::Error, history::{self, HistoryEntry}, output::{self, Output}};
+
+use super::{confirm_changes, format_elapsed_time, Command};
+
+pub struct HistoryCommand {
+    pub project: String,
+}
+
+pub struct UndoCommand {
+    pub project: String,
+    pub to: Option<u64>,
+    pub yes: bool,
+}
+
+#[derive(Serialize)]
+struct HistoryResult {
+    project: String,
+    /// The most recent first
+    entries: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize)]
+struct UndoResult {
+    project: String,
+    /// Whether the files were restored (they are not when the undo was not confirmed)
+    restored: bool,
+    /// The undone changes, the most recent first
+    undone: Vec<HistoryEntry>,
+}
+
+impl Output for HistoryResult {
+
+    fn print_text(&self) {
+        if self.entries.is_empty() {
+            println!("The '{}' project has no history yet", self.project);
+            return;
+        }
+
+        for entry in &self.entries {
+            println!("{}\t{}\t{}", entry.id, format_entry_time(entry), entry.description);
+        }
+    }
+
+}
+
+impl Output for UndoResult {
+
+    fn print_text(&self) {
+        match (self.restored, self.undone.last()) {
+            (true, Some(entry)) => println!("Restored the '{}' project to its state before: {} ({})", self.project, entry.description, format_entry_time(entry)),
+            _ => println!("Nothing was restored"),
+        }
+    }
+
+}
+
+impl Command for HistoryCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Listing the history of the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        if !config::exists(&self.project)? {
+            return Err(Error::ConfigNotFound(format!("The project '{}' does not exist", self.project)).into());
+        }
+
+        output::print(&HistoryResult {
+            project: self.project.to_string(),
+            entries: history::list(&self.project)?,
+        })
+    }
+
+}
+
+impl Command for UndoCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Undoing changes to the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        if !config::exists(&self.project)? {
+            return Err(Error::ConfigNotFound(format!("The project '{}' does not exist", self.project)).into());
+        }
+
+        let mut entries = history::list(&self.project)?;
+        let count = match self.to {
+            Some(id) => entries.iter().position(|entry| entry.id == id)
+                .map(|index| index + 1)
+                .ok_or(Error::HistoryNotFound(format!("The '{}' project has no change with id {} in its history (see 'dbml history {}')", self.project, id, self.project)))?,
+            None if entries.is_empty() => return Err(Error::HistoryNotFound(format!("The '{}' project has no change to undo", self.project)).into()),
+            None => 1,
+        };
+        entries.truncate(count);
+
+        let mut result = UndoResult {
+            project: self.project.to_string(),
+            restored: false,
+            undone: entries,
+        };
+
+        if !self.yes && !config::is_dry_run() {
+            let changes = result.undone.iter().map(|entry| format!("{} ({})", entry.description, format_entry_time(entry)));
+            if !confirm_changes("The following changes will be undone:", changes)? {
+                return output::print(&result);
+            }
+        }
+
+        if let Some(entry) = result.undone.last() {
+            history::restore(&self.project, entry)?;
+        }
+        for entry in &result.undone {
+            history::remove(&self.project, entry)?;
+        }
+
+        result.restored = true;
+        output::print(&result)
+    }
+
+}
+
+fn format_entry_time(entry: &HistoryEntry) -> String {
+    format_elapsed_time(Some(entry.id / 1000)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+    use crate::{commander::Command, config, error, history};
+
+    use super::UndoCommand;
+
+    const PROJECT_FILE: &str = "\
+version: 2
+databases:
+  orders:
+    connection:
+      type: mysql
+      host: localhost
+      port: 3306
+      username: root
+      password: root
+  users:
+    connection:
+      type: mysql
+      host: localhost
+      port: 3306
+      username: root
+      password: root
+";
+
+    /// A `shop` project with two databases that were never scanned, in a config directory of its own
+    fn create_project(name: &str) -> (PathBuf, String) {
+        let directory = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        config::set_test_config_dir(directory.clone());
+        fs::write(directory.join("shop.yaml"), PROJECT_FILE).unwrap();
+        (directory, String::from("shop"))
+    }
+
+    /// Saves the project with the given fingerprints in the scan stores of its databases
+    fn save(project: &String, fingerprints: &[(&str, &str)]) {
+        let mut config = config::load(project).unwrap();
+        for (database_name, fingerprint) in fingerprints {
+            config.databases.get_mut(*database_name).unwrap().fingerprints = Some(BTreeMap::from([(String::from("main"), fingerprint.to_string())]));
+        }
+        config.save().unwrap();
+    }
+
+    async fn undo(project: &String, to: Option<u64>) -> anyhow::Result<()> {
+        UndoCommand { project: project.to_string(), to, yes: true }.execute().await
+    }
+
+    #[tokio::test]
+    async fn test_undo_restores_the_files_before_the_last_change() {
+        let (directory, project) = create_project("dbml_test_undo");
+        let store_dir = directory.join("shop");
+        save(&project, &[("orders", "1")]);
+        save(&project, &[("orders", "2"), ("users", "3")]);
+        assert_eq!(history::list(&project).unwrap().len(), 2);
+
+        undo(&project, None).await.unwrap();
+
+        assert!(fs::read_to_string(store_dir.join("orders.yaml")).unwrap().contains("main: '1'"));
+        assert!(!store_dir.join("users.yaml").exists());
+        assert_eq!(fs::read_to_string(directory.join("shop.yaml")).unwrap(), PROJECT_FILE);
+        assert_eq!(history::list(&project).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_undo_to_an_entry() {
+        let (directory, project) = create_project("dbml_test_undo_to");
+        save(&project, &[("orders", "1")]);
+        save(&project, &[("orders", "2"), ("users", "3")]);
+        let first_entry = history::list(&project).unwrap().pop().unwrap();
+
+        let error = undo(&project, Some(first_entry.id + 1000)).await.unwrap_err();
+        assert_eq!(error::get_exit_code(&error), 9);
+
+        undo(&project, Some(first_entry.id)).await.unwrap();
+
+        assert!(!directory.join("shop").join("orders.yaml").exists());
+        assert!(!directory.join("shop").join("users.yaml").exists());
+        assert!(history::list(&project).unwrap().is_empty());
+
+        let error = undo(&project, None).await.unwrap_err();
+        assert_eq!(error::get_exit_code(&error), 9);
+    }
+
+    #[test]
+    fn test_old_entries_are_evicted() {
+        let (directory, project) = create_project("dbml_test_history_eviction");
+        let store_dir = directory.join("shop");
+        fs::create_dir_all(&store_dir).unwrap();
+        for fingerprint in 0..22 {
+            fs::write(store_dir.join("orders.yaml"), format!("fingerprints:\n  main: '{}'\n", fingerprint)).unwrap();
+            history::record(&project).unwrap();
+        }
+
+        let entries = history::list(&project).unwrap();
+        assert_eq!(entries.len(), 20);
+
+        // The two oldest entries are gone
+        let store_path = store_dir.join(".history").join(entries[19].id.to_string()).join("stores").join("orders.yaml");
+        assert!(fs::read_to_string(store_path).unwrap().contains("main: '2'"));
+    }
+
+}
@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use crate::{config::{self, DatabaseConfiguration, FileConfig, ProjectDatabase, ProjectDatabaseConnection, CURRENT_VERSION}, db::{self, DatabaseType}, log, output::{self, Output}};
+
+use super::{ask, ask_password, confirm, Command};
+
+#[derive(Serialize)]
+struct InitResult {
+    project: String,
+    path: PathBuf,
+    databases: Vec<String>,
+}
+
+impl Output for InitResult {
+
+    fn print_text(&self) {
+        println!("Project '{}' created at {}", self.project, self.path.display());
+    }
+
+}
+
+pub struct InitCommand {
+    pub project: String,
+    pub non_interactive: bool,
+    pub database: Option<String>,
+    pub r#type: DatabaseType,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub ignore_schemas: Vec<String>,
+    pub skip_connection_test: bool,
+    pub force: bool,
+}
+
+impl Command for InitCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Creating the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        if config::exists(&self.project)? && !self.force {
+            return Err(anyhow!("The project '{}' already exists (use --force to overwrite it)", self.project));
+        }
+
+        let databases = match self.non_interactive {
+            true => self.get_database_from_flags().await?,
+            false => ask_for_databases().await?,
+        };
+
+        let config = FileConfig {
+            version: CURRENT_VERSION,
+            include: None,
+            configurations: None,
+            databases,
+            custom_references: None,
+        };
+
+        let path = config::create(&self.project, &config, self.force)?;
+        output::print(&InitResult {
+            project: self.project.to_string(),
+            path,
+            databases: config.databases.keys().cloned().collect(),
+        })?;
+
+        log::info(format!("Run 'dbml scan {}' to scan its databases", self.project));
+        Ok(())
+    }
+
+}
+
+impl InitCommand {
+
+    async fn get_database_from_flags(&self) -> Result<BTreeMap<String, ProjectDatabase>> {
+        let (database_name, username, password) = match (&self.database, &self.username, &self.password) {
+            (Some(database_name), Some(username), Some(password)) => (database_name, username, password),
+            _ => return Err(anyhow!("--database, --username and --password are required with --non-interactive")),
+        };
+
+        let connection = ProjectDatabaseConnection {
+            r#type: self.r#type.clone(),
+            host: self.host.to_string(),
+            port: self.port,
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+
+        if !self.skip_connection_test {
+            let schemas = test_connection(&connection).await?;
+            for schema_name in &self.ignore_schemas {
+                if !schemas.contains(schema_name) {
+                    log::warn(format!("The schema '{}' to be ignored does not exist in database {}", schema_name, database_name));
+                }
+            }
+        }
+
+        let mut databases: BTreeMap<String, ProjectDatabase> = BTreeMap::new();
+        databases.insert(database_name.to_string(), new_database(connection, self.ignore_schemas.clone()));
+        Ok(databases)
+    }
+
+}
+
+async fn ask_for_databases() -> Result<BTreeMap<String, ProjectDatabase>> {
+    let mut databases: BTreeMap<String, ProjectDatabase> = BTreeMap::new();
+
+    loop {
+        let database_name = ask("Database name:", None)?;
+        if database_name.is_empty() {
+            eprintln!("The database name cannot be empty");
+            continue;
+        }
+        if databases.contains_key(&database_name) {
+            eprintln!("The database '{}' was already added", database_name);
+            continue;
+        }
+
+        let (connection, schemas) = ask_for_connection(&database_name).await?;
+        let schemas_to_ignore = match schemas {
+            Some(schemas) if !schemas.is_empty() && confirm(&format!("Do you want to choose schemas of {} to be ignored by the scans?", database_name))? =>
+                ask_for_schemas_to_ignore(&schemas)?,
+            _ => Vec::new(),
+        };
+
+        databases.insert(database_name, new_database(connection, schemas_to_ignore));
+
+        if !confirm("Do you want to add another database?")? {
+            return Ok(databases);
+        }
+    }
+}
+
+/// Asks for the connection details of a database until they work (or the user keeps them anyway),
+/// returning them with the schemas of the server when the connection could be tested
+async fn ask_for_connection(database_name: &str) -> Result<(ProjectDatabaseConnection, Option<Vec<String>>)> {
+    loop {
+        let r#type = loop {
+            match ask("Database type:", Some(DatabaseType::MySql.as_string()))?.parse::<DatabaseType>() {
+                Ok(r#type) => break r#type,
+                Err(err) => eprintln!("{}", err),
+            }
+        };
+
+        let host = ask("Host:", Some("localhost"))?;
+        let port = loop {
+            match ask("Port:", Some("3306"))?.parse::<u16>() {
+                Ok(port) => break port,
+                Err(_) => eprintln!("The port must be a number between 0 and 65535"),
+            }
+        };
+        let username = ask("Username:", None)?;
+        let password = ask_password("Password:")?;
+
+        let connection = ProjectDatabaseConnection { r#type, host, port, username, password };
+
+        match test_connection(&connection).await {
+            Ok(schemas) => return Ok((connection, Some(schemas))),
+            Err(err) => {
+                eprintln!("{}", err);
+                if !confirm(&format!("Do you want to enter the connection details of {} again?", database_name))? {
+                    return Ok((connection, None));
+                }
+            }
+        }
+    }
+}
+
+fn ask_for_schemas_to_ignore(schemas: &[String]) -> Result<Vec<String>> {
+    for (index, schema_name) in schemas.iter().enumerate() {
+        eprintln!("\t{}. {}", index + 1, schema_name);
+    }
+
+    loop {
+        let answer = ask("Schemas to be ignored (numbers or names separated by commas):", None)?;
+        let mut schemas_to_ignore: Vec<String> = Vec::new();
+        let mut invalid: Vec<&str> = Vec::new();
+
+        for choice in answer.split(',').map(str::trim).filter(|choice| !choice.is_empty()) {
+            let schema_name = match choice.parse::<usize>() {
+                Ok(number) => schemas.get(number.wrapping_sub(1)),
+                Err(_) => schemas.iter().find(|schema_name| *schema_name == choice),
+            };
+
+            match schema_name {
+                Some(schema_name) if !schemas_to_ignore.contains(schema_name) => schemas_to_ignore.push(schema_name.to_string()),
+                Some(_) => {}
+                None => invalid.push(choice),
+            }
+        }
+
+        if invalid.is_empty() {
+            return Ok(schemas_to_ignore);
+        }
+        eprintln!("Not a schema of the list: {}", invalid.join(", "));
+    }
+}
+
+/// Connects to the database, returning the schemas of the server
+async fn test_connection(connection: &ProjectDatabaseConnection) -> Result<Vec<String>> {
+    // Values may reference environment variables, which are only expanded when the project is loaded
+    let mut expanded_connection = connection.clone();
+    expanded_connection.host = config::interpolate(&connection.host)?;
+    expanded_connection.username = config::interpolate(&connection.username)?;
+    expanded_connection.password = config::interpolate(&connection.password)?;
+
+    log::info(format!("Testing the connection to {}", expanded_connection));
+    let schemas = db::list_schemas(expanded_connection).await?;
+    log::info(format!("Connected, the server has {} schemas", schemas.len()));
+    Ok(schemas)
+}
+
+fn new_database(connection: ProjectDatabaseConnection, schemas_to_ignore: Vec<String>) -> ProjectDatabase {
+    let configurations = match schemas_to_ignore.is_empty() {
+        true => None,
+        false => Some(DatabaseConfiguration { schemas_to_ignore: Some(schemas_to_ignore) }),
+    };
+
+    ProjectDatabase::new(connection, configurations)
+}
@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+use crate::{config::{self, Config, CURRENT_VERSION}, log, output::{self, Output}};
+
+use super::Command;
+
+pub struct MigrateCommand {
+    pub project: String,
+}
+
+#[derive(Serialize)]
+struct MigrateResult {
+    project: String,
+    from_version: u32,
+    to_version: u32,
+    /// The copy of the file before the migration, `None` when it was already at the current version
+    backup: Option<PathBuf>,
+}
+
+impl Output for MigrateResult {
+
+    fn print_text(&self) {
+        match self.from_version == self.to_version {
+            true => println!("The config file is already at version {}", self.to_version),
+            false => println!("Migrated the config file from version {} to version {}", self.from_version, self.to_version),
+        }
+    }
+
+}
+
+impl Command for MigrateCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Migrating the config file of the '{}' project to version {}", self.project, CURRENT_VERSION)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut config: Config = config::load(&self.project)?;
+        let mut result = MigrateResult {
+            project: self.project.to_string(),
+            from_version: config.get_source_version(),
+            to_version: CURRENT_VERSION,
+            backup: None,
+        };
+        if result.from_version == CURRENT_VERSION {
+            return output::print(&result);
+        }
+
+        let backup_path = config.backup(result.from_version)?;
+        log::info(format!("Backed up the version {} config file to {}", result.from_version, backup_path.display()));
+        result.backup = Some(backup_path);
+
+        config.upgrade();
+        config.save()?;
+        output::print(&result)
+    }
+
+}
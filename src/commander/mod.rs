@@ -1,11 +1,20 @@
-use anyhow::Result;
-use crate::cli::{Cli, SubCommands};
-use self::{clean::CleanCommand, generate::GenerateCommand, scan::ScanCommand, search::SearchCommand, validate::ValidateCommand};
+use std::{fmt, io::{self, Write}, time::{SystemTime, UNIX_EPOCH}};
+
+use anyhow::{anyhow, Result};
+use crate::{cli::{Cli, ConfigSubCommands, ProjectSubCommands, SubCommands}, config, log::{self, Phase}, output};
+use self::{clean::CleanCommand, generate::GenerateCommand, history::{HistoryCommand, UndoCommand}, init::InitCommand, migrate::MigrateCommand, project::{ProjectCopyCommand, ProjectDeleteCommand, ProjectListCommand, ProjectRenameCommand, ProjectShowCommand}, scan::ScanCommand, schema::SchemaCommand, settings::{ConfigGetCommand, ConfigSetCommand, ConfigUnsetCommand}, search::SearchCommand, stale::StaleCommand, validate::ValidateCommand};
 
 mod clean;
 mod scan;
+mod schema;
+mod settings;
 mod search;
+mod stale;
 mod generate;
+mod history;
+mod init;
+mod migrate;
+mod project;
 mod validate;
 
 pub trait Command {
@@ -14,11 +23,113 @@ pub trait Command {
 }
 
 pub async fn execute(cli: Cli) -> Result<()> {
+    config::set_config_dir(cli.config_dir);
+    config::set_profile(cli.profile);
+    config::set_dry_run(cli.dry_run);
+    log::init(cli.quiet, cli.verbose, cli.log_format);
+    output::set_format(cli.format);
+
     match cli.command {
-        SubCommands::Scan { project } => ScanCommand { project }.execute().await,
-        SubCommands::Generate { project, starting_table } => GenerateCommand { project, starting_table }.execute().await,
-        SubCommands::Search { project, regex, referenced_key } => SearchCommand { project, regex, referenced_key }.execute().await,
-        SubCommands::Validate { project } => ValidateCommand { project }.execute().await,
-        SubCommands::Clean { project } => CleanCommand { project }.execute().await,
+        SubCommands::Scan { project, full, databases, schemas, tables } => run(ScanCommand { project, full, databases, schemas, tables }).await,
+        SubCommands::Generate { project, starting_table, workspace } => run(GenerateCommand { project, starting_table, workspace }).await,
+        SubCommands::Search { project, regex, referenced_key, workspace } => run(SearchCommand { project, regex, referenced_key, workspace }).await,
+        SubCommands::Validate { project, workspace } => run(ValidateCommand { project, workspace }).await,
+        SubCommands::Stale { project, prune, rewrite } => run(StaleCommand { project, prune, rewrite }).await,
+        SubCommands::Clean { project, databases, schemas, custom_references, yes } => run(CleanCommand { project, databases, schemas, custom_references, yes }).await,
+        SubCommands::Init { project, non_interactive, database, r#type, host, port, username, password, ignore_schemas, skip_connection_test, force } => run(InitCommand {
+            project, non_interactive, database, r#type, host, port, username, password, ignore_schemas, skip_connection_test, force
+        }).await,
+        SubCommands::Project { command } => match command {
+            ProjectSubCommands::List => run(ProjectListCommand).await,
+            ProjectSubCommands::Show { project } => run(ProjectShowCommand { project }).await,
+            ProjectSubCommands::Copy { project, new_project } => run(ProjectCopyCommand { project, new_project }).await,
+            ProjectSubCommands::Rename { project, new_project } => run(ProjectRenameCommand { project, new_project }).await,
+            ProjectSubCommands::Delete { project, yes } => run(ProjectDeleteCommand { project, yes }).await,
+        },
+        SubCommands::Config { command } => match command {
+            ConfigSubCommands::Get { project, path } => run(ConfigGetCommand { project, path }).await,
+            ConfigSubCommands::Set { project, path, value, append } => run(ConfigSetCommand { project, path, value, append }).await,
+            ConfigSubCommands::Unset { project, path } => run(ConfigUnsetCommand { project, path }).await,
+        },
+        SubCommands::History { project } => run(HistoryCommand { project }).await,
+        SubCommands::Undo { project, to, yes } => run(UndoCommand { project, to, yes }).await,
+        SubCommands::Migrate { project } => run(MigrateCommand { project }).await,
+        SubCommands::Schema { store, output } => run(SchemaCommand { store, output }).await,
+    }
+}
+
+async fn run(command: impl Command) -> Result<()> {
+    let message = command.get_starting_message();
+    log::info(&message);
+    crate::history::set_description(message.to_string());
+
+    let phase = Phase::start(message);
+    let result = command.execute().await;
+    phase.finish();
+
+    if config::is_dry_run() {
+        log::info("Dry run: no file was written");
     }
-}
\ No newline at end of file
+    result
+}
+
+/// Asks a yes/no question on the terminal, defaulting to "no"
+fn confirm(question: &str) -> Result<bool> {
+    let answer = ask(&format!("{} [y/N]", question), None)?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Lists what a command is about to change, one item per indented line under `header`, and asks
+/// whether to continue (defaulting to "no")
+fn confirm_changes(header: &str, items: impl IntoIterator<Item = impl fmt::Display>) -> Result<bool> {
+    // Part of the question, so it goes to stderr with it
+    eprintln!("{}", header);
+    for item in items {
+        eprintln!("\t{}", item);
+    }
+
+    confirm("Do you want to continue?")
+}
+
+/// Asks a question on the terminal, returning the trimmed answer (or `default`, when the answer is empty).
+/// The question is written on stderr, like the logs, so stdout only carries the result of the command.
+fn ask(question: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => eprint!("{} [{}] ", question, default),
+        None => eprint!("{} ", question),
+    }
+    io::stderr().flush()
+        .map_err(|err| anyhow!(format!("Could not write to the terminal: {}", err)))?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)
+        .map_err(|err| anyhow!(format!("Could not read the answer from the terminal: {}", err)))?;
+
+    match (answer.trim(), default) {
+        ("", Some(default)) => Ok(default.to_string()),
+        (answer, _) => Ok(answer.to_string()),
+    }
+}
+
+/// Asks for a password on the terminal without echoing it
+fn ask_password(question: &str) -> Result<String> {
+    rpassword::prompt_password(format!("{} ", question))
+        .map_err(|err| anyhow!(format!("Could not read the password from the terminal: {}", err)))
+}
+
+fn get_timestamp(time: Option<SystemTime>) -> Option<u64> {
+    time.map(|time| time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default())
+}
+
+/// How long ago a moment (in seconds since the Unix epoch) was, roughly ("3 hours ago")
+fn format_elapsed_time(timestamp: Option<u64>) -> Option<String> {
+    let now = get_timestamp(Some(SystemTime::now())).unwrap_or_default();
+    let elapsed = now.saturating_sub(timestamp?);
+
+    Some(match elapsed {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{} minutes ago", elapsed / 60),
+        3600..=86399 => format!("{} hours ago", elapsed / 3600),
+        _ => format!("{} days ago", elapsed / 86400),
+    })
+}
@@ -0,0 +1,396 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use crate::{config::{self, Config}, error::Error, log, output::{self, Output}};
+
+use super::{confirm_changes, format_elapsed_time, get_timestamp, Command};
+
+pub struct ProjectListCommand;
+
+pub struct ProjectShowCommand {
+    pub project: String,
+}
+
+pub struct ProjectCopyCommand {
+    pub project: String,
+    pub new_project: String,
+}
+
+pub struct ProjectRenameCommand {
+    pub project: String,
+    pub new_project: String,
+}
+
+pub struct ProjectDeleteCommand {
+    pub project: String,
+    pub yes: bool,
+}
+
+#[derive(Serialize)]
+struct ProjectListResult {
+    config_dir: PathBuf,
+    projects: Vec<ProjectSummary>,
+}
+
+#[derive(Serialize)]
+struct ProjectSummary {
+    project: String,
+    /// `None` when the project could not be loaded (see `error`)
+    databases: Option<Vec<String>>,
+    /// Seconds since the Unix epoch, `None` when the project was never scanned
+    last_scan_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProjectShowResult {
+    project: String,
+    files: Vec<PathBuf>,
+    version: u32,
+    /// Seconds since the Unix epoch, `None` when the project was never scanned
+    last_scan_time: Option<u64>,
+    databases: Vec<DatabaseSummary>,
+    references: usize,
+    custom_references: usize,
+}
+
+#[derive(Serialize)]
+struct DatabaseSummary {
+    database_name: String,
+    r#type: String,
+    host: String,
+    port: u16,
+    username: String,
+    profiles: Vec<String>,
+    schemas_to_ignore: Vec<String>,
+    /// `None` when the database was not scanned yet
+    tables: Option<usize>,
+    columns: Option<usize>,
+}
+
+/// The files of a project copied (or moved) to the ones of a new project
+#[derive(Serialize)]
+struct ProjectCopyResult {
+    project: String,
+    new_project: String,
+    /// `copied` or `renamed`
+    action: &'static str,
+    files: Vec<CopiedFile>,
+}
+
+#[derive(Serialize)]
+struct CopiedFile {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+#[derive(Serialize)]
+struct ProjectDeleteResult {
+    project: String,
+    /// Whether the files were deleted (they are not when the deletion was not confirmed)
+    deleted: bool,
+    files: Vec<PathBuf>,
+}
+
+impl Output for ProjectListResult {
+
+    fn print_text(&self) {
+        if self.projects.is_empty() {
+            println!("There are no projects in {}", self.config_dir.display());
+            return;
+        }
+
+        println!("Projects in {}:", self.config_dir.display());
+        for project in &self.projects {
+            let last_scan = match format_elapsed_time(project.last_scan_time) {
+                Some(elapsed_time) => format!("last scanned {}", elapsed_time),
+                None => String::from("never scanned"),
+            };
+            match (&project.databases, &project.error) {
+                (Some(databases), _) => println!("\t{}: {} ({})", project.project, databases.join(", "), last_scan),
+                (None, error) => println!("\t{}: could not be loaded ({})", project.project, error.as_deref().unwrap_or_default()),
+            }
+        }
+    }
+
+}
+
+impl Output for ProjectShowResult {
+
+    fn print_text(&self) {
+        println!("Files:");
+        for path in &self.files {
+            println!("\t{}", path.display());
+        }
+        println!("Version: {}", self.version);
+        println!("Last scan: {}", format_elapsed_time(self.last_scan_time).unwrap_or(String::from("never")));
+
+        println!("Databases:");
+        for database in &self.databases {
+            println!("\t{}: {} at {}:{} as {}", database.database_name, database.r#type, database.host, database.port, database.username);
+
+            if !database.profiles.is_empty() {
+                println!("\t\tProfiles: {}", database.profiles.join(", "));
+            }
+
+            if !database.schemas_to_ignore.is_empty() {
+                println!("\t\tIgnored schemas: {}", database.schemas_to_ignore.join(", "));
+            }
+
+            match (database.tables, database.columns) {
+                (Some(tables), Some(columns)) => println!("\t\t{} tables, {} columns", tables, columns),
+                _ => println!("\t\tNot scanned yet"),
+            }
+        }
+
+        println!("References: {}", self.references);
+        println!("Custom references: {}", self.custom_references);
+    }
+
+}
+
+impl Output for ProjectCopyResult {
+
+    fn print_text(&self) {
+        match config::is_dry_run() {
+            true => println!("Project '{}' would be {} to '{}':", self.project, self.action, self.new_project),
+            false => println!("Project '{}' {} to '{}':", self.project, self.action, self.new_project),
+        }
+        for file in &self.files {
+            println!("\t{} -> {}", file.from.display(), file.to.display());
+        }
+    }
+
+}
+
+impl Output for ProjectDeleteResult {
+
+    fn print_text(&self) {
+        match self.deleted {
+            true => println!("Project '{}' deleted", self.project),
+            false => println!("Nothing was deleted"),
+        }
+    }
+
+}
+
+impl Command for ProjectListCommand {
+
+    fn get_starting_message(&self) -> String {
+        String::from("Listing the projects")
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut projects: Vec<ProjectSummary> = Vec::new();
+        for project in config::list_projects()? {
+            let last_scan_time = get_timestamp(config::get_last_scan_time(&project)?);
+            let summary = match config::load(&project) {
+                Ok(config) => ProjectSummary {
+                    project,
+                    databases: Some(config.databases.keys().cloned().collect()),
+                    last_scan_time,
+                    error: None,
+                },
+                Err(err) => ProjectSummary {
+                    project,
+                    databases: None,
+                    last_scan_time,
+                    error: Some(format!("{:#}", err)),
+                },
+            };
+            projects.push(summary);
+        }
+
+        output::print(&ProjectListResult {
+            config_dir: config::get_config_dir()?,
+            projects,
+        })
+    }
+
+}
+
+impl Command for ProjectShowCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Showing the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let config: Config = config::load(&self.project)?;
+
+        let mut databases: Vec<DatabaseSummary> = Vec::new();
+        for (database_name, database) in &config.databases {
+            let connection = &database.connection;
+            databases.push(DatabaseSummary {
+                database_name: database_name.to_string(),
+                r#type: connection.r#type.as_string().to_string(),
+                host: connection.host.to_string(),
+                port: connection.port,
+                username: connection.username.to_string(),
+                profiles: database.profiles.iter().flat_map(|profiles| profiles.keys().cloned()).collect(),
+                schemas_to_ignore: database.configurations.as_ref()
+                    .and_then(|configurations| configurations.schemas_to_ignore.clone())
+                    .unwrap_or_default(),
+                tables: database.tables.as_ref().map(|tables| tables.len()),
+                columns: database.tables.as_ref().map(|tables| tables.values().map(|table| table.columns.len()).sum()),
+            });
+        }
+
+        output::print(&ProjectShowResult {
+            project: self.project.to_string(),
+            files: config::get_project_paths(&self.project)?,
+            version: config.get_source_version(),
+            last_scan_time: get_timestamp(config::get_last_scan_time(&self.project)?),
+            databases,
+            references: count_references(&config.references),
+            custom_references: count_references(&config.custom_references),
+        })
+    }
+
+}
+
+impl Command for ProjectCopyCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Copying the '{}' project to '{}'", self.project, self.new_project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut files: Vec<CopiedFile> = Vec::new();
+        for (path, new_path) in get_new_paths(&self.project, &self.new_project)? {
+            log::info(format!("Copying {} to {}", path.display(), new_path.display()));
+            if !config::is_dry_run() {
+                copy(&path, &new_path)?;
+            }
+            files.push(CopiedFile { from: path, to: new_path });
+        }
+
+        output::print(&ProjectCopyResult {
+            project: self.project.to_string(),
+            new_project: self.new_project.to_string(),
+            action: "copied",
+            files,
+        })
+    }
+
+}
+
+impl Command for ProjectRenameCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Renaming the '{}' project to '{}'", self.project, self.new_project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut files: Vec<CopiedFile> = Vec::new();
+        for (path, new_path) in get_new_paths(&self.project, &self.new_project)? {
+            log::info(format!("Moving {} to {}", path.display(), new_path.display()));
+            if !config::is_dry_run() {
+                fs::rename(&path, &new_path)
+                    .map_err(|err| anyhow!(format!("Could not move '{}' to '{}': {}", path.display(), new_path.display(), err)))?;
+            }
+            files.push(CopiedFile { from: path, to: new_path });
+        }
+
+        output::print(&ProjectCopyResult {
+            project: self.project.to_string(),
+            new_project: self.new_project.to_string(),
+            action: "renamed",
+            files,
+        })
+    }
+
+}
+
+impl Command for ProjectDeleteCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Deleting the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        if !config::exists(&self.project)? {
+            return Err(Error::ConfigNotFound(format!("The project '{}' does not exist", self.project)).into());
+        }
+
+        let mut result = ProjectDeleteResult {
+            project: self.project.to_string(),
+            deleted: false,
+            files: config::get_project_paths(&self.project)?,
+        };
+
+        if config::is_dry_run() {
+            for path in &result.files {
+                log::info(format!("Would delete {}", path.display()));
+            }
+            return output::print(&result);
+        }
+
+        if !self.yes && !confirm_changes("The following will be deleted:", result.files.iter().map(|path| path.display()))? {
+            return output::print(&result);
+        }
+
+        for path in &result.files {
+            log::info(format!("Deleting {}", path.display()));
+            let result = match path.is_dir() {
+                true => fs::remove_dir_all(path),
+                false => fs::remove_file(path),
+            };
+            result.map_err(|err| anyhow!(format!("Could not delete '{}': {}", path.display(), err)))?;
+        }
+
+        result.deleted = true;
+        output::print(&result)
+    }
+
+}
+
+/// Pairs each file of a project with the path it has in a project named `new_project`, which must not exist yet
+fn get_new_paths(project: &String, new_project: &String) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if !config::exists(project)? {
+        return Err(Error::ConfigNotFound(format!("The project '{}' does not exist", project)).into());
+    }
+    if new_project.is_empty() || new_project.contains(['/', '\\']) {
+        return Err(anyhow!("'{}' is not a valid project name", new_project));
+    }
+    if !config::get_project_paths(new_project)?.is_empty() {
+        return Err(anyhow!("The project '{}' already exists", new_project));
+    }
+
+    let mut new_paths: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for path in config::get_project_paths(project)? {
+        // Every file of a project is named after it: {project}.yaml, {project}/, {project}.dbml...
+        let file_name = path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+        let new_file_name = format!("{}{}", new_project, &file_name[project.len()..]);
+        let new_path = path.with_file_name(new_file_name);
+        new_paths.push((path, new_path));
+    }
+
+    Ok(new_paths)
+}
+
+fn copy(path: &Path, new_path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::create_dir_all(new_path)
+            .map_err(|err| anyhow!(format!("Could not create the directory '{}': {}", new_path.display(), err)))?;
+        let entries = fs::read_dir(path)
+            .map_err(|err| anyhow!(format!("Could not read the directory '{}': {}", path.display(), err)))?;
+        for entry in entries.flatten() {
+            copy(&entry.path(), &new_path.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    fs::copy(path, new_path)
+        .map_err(|err| anyhow!(format!("Could not copy '{}' to '{}': {}", path.display(), new_path.display(), err)))?;
+    Ok(())
+}
+
+fn count_references(references: &Option<BTreeMap<String, Vec<String>>>) -> usize {
+    references.iter()
+        .flat_map(|references| references.values())
+        .map(Vec::len)
+        .sum()
+}
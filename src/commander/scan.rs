@@ -1,10 +1,79 @@
-use std::collections::HashMap;
+use std::{collections::{BTreeMap, BTreeSet}, time::{Instant, SystemTime}};
 
-use anyhow::Result;
-use crate::{commander::Command, config::{self, Config, ProjectDatabaseColumn, ProjectDatabaseTable, ProjectDatabaseIndex}, db::{self}};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Serialize;
+use crate::{commander::{get_timestamp, Command}, error::Error, key::{self, ColumnKey, TableKey}, log::{self, Phase, Progress}, output::{self, Output}, config::{self, Config, ProjectConfiguration, ProjectDatabase, ProjectDatabaseColumn, ProjectDatabaseTable, ProjectDatabaseIndex}, db::{self}};
 
 pub struct ScanCommand {
-    pub project: String
+    pub project: String,
+    pub full: bool,
+    pub databases: Vec<String>,
+    pub schemas: Vec<String>,
+    pub tables: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ScanResult {
+    project: String,
+    databases: Vec<DatabaseScan>,
+    composite_primary_keys: Vec<CompositePrimaryKey>,
+}
+
+/// What the scan of a database went through
+#[derive(Serialize)]
+struct DatabaseScan {
+    database_name: String,
+    /// Whether the database was skipped, because none of its schemas changed since the last scan
+    skipped: bool,
+    /// The (re)scanned schemas, or `None` when all of them were
+    schemas: Option<Vec<String>>,
+    tables: usize,
+    columns: usize,
+    references: usize,
+    /// The rows read from the database while scanning the columns
+    rows: u64,
+    dropped_tables: Vec<String>,
+    dropped_columns: Vec<String>,
+    elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+struct CompositePrimaryKey {
+    table_key: String,
+    columns: Vec<String>,
+}
+
+impl DatabaseScan {
+
+    fn new(database_name: &str) -> Self {
+        DatabaseScan {
+            database_name: database_name.to_string(),
+            skipped: false,
+            schemas: None,
+            tables: 0,
+            columns: 0,
+            references: 0,
+            rows: 0,
+            dropped_tables: Vec::new(),
+            dropped_columns: Vec::new(),
+            elapsed_ms: 0,
+        }
+    }
+
+}
+
+impl Output for ScanResult {
+
+    fn print_text(&self) {
+        for database in &self.databases {
+            match database.skipped {
+                true => println!("Database {}: no schema changed since the last scan", database.database_name),
+                false => println!("Database {}: {} tables, {} columns and {} references scanned", database.database_name, database.tables, database.columns, database.references),
+            }
+        }
+    }
+
 }
 
 impl Command for ScanCommand {
@@ -15,97 +84,281 @@ impl Command for ScanCommand {
 
     async fn execute(&self) -> Result<()> {
         let mut config: Config = config::load(&self.project)?;
-        scan_tables_and_columns(&mut config).await?;
-        search_for_composite_primary_keys(&mut config);
-        scan_references(&mut config).await?;
-        config.save()
+
+        for database_name in &self.databases {
+            if !config.databases.contains_key(database_name) {
+                return Err(Error::InvalidKey(format!("The database '{}' does not exist in the config file for project {}", database_name, self.project)).into());
+            }
+        }
+
+        let mut table_patterns: Vec<Regex> = Vec::new();
+        for table in &self.tables {
+            table_patterns.push(parse_table_pattern(table)?);
+        }
+
+        let mut database_scans: Vec<DatabaseScan> = Vec::new();
+        let Config { configurations, databases, references, .. } = &mut config;
+        for (database_name, database) in databases {
+            if !self.databases.is_empty() && !self.databases.contains(database_name) {
+                continue;
+            }
+
+            let start = Instant::now();
+            let mut database_scan = DatabaseScan::new(database_name);
+            let scope = if self.schemas.is_empty() && table_patterns.is_empty() {
+                match get_schemas_to_scan(database_name, database, configurations, self.full).await? {
+                    Some(scope) => scope,
+                    None => {
+                        database.scanned_at = get_timestamp(Some(SystemTime::now()));
+                        database_scan.skipped = true;
+                        database_scans.push(database_scan);
+                        continue;
+                    }
+                }
+            }
+            else {
+                ScanScope {
+                    schemas: Some(self.schemas.clone()).filter(|schemas| !schemas.is_empty()),
+                    tables: Some(table_patterns.clone()).filter(|tables| !tables.is_empty()),
+                }
+            };
+
+            database_scan.schemas = scope.schemas.clone();
+            scan_tables_and_columns(database_name, database, configurations, &scope, &mut database_scan).await?;
+            scan_references(database_name, database, configurations, references, &scope, &mut database_scan).await?;
+            database.scanned_at = get_timestamp(Some(SystemTime::now()));
+            database_scan.elapsed_ms = start.elapsed().as_millis() as u64;
+            database_scans.push(database_scan);
+        }
+
+        let composite_primary_keys = search_for_composite_primary_keys(&mut config);
+
+        let phase = Phase::start(format!("Saving the config file of project '{}'", self.project));
+        config.save()?;
+        phase.finish();
+
+        output::print(&ScanResult {
+            project: self.project.to_string(),
+            databases: database_scans,
+            composite_primary_keys,
+        })
     }
 
 }
 
-async fn scan_tables_and_columns(config: &mut Config) -> Result<()> {
-    for (database_name, database) in &mut config.databases {
-        println!("Scanning database {} at {}", database_name, database.connection.get_connection_string());
+/// The part of a database that must be (re)scanned: every table of the listed schemas (or of all
+/// schemas, when `None`) whose name matches one of the table patterns (or any name, when `None`).
+/// Tables out of the scope are kept as they are.
+struct ScanScope {
+    schemas: Option<Vec<String>>,
+    tables: Option<Vec<Regex>>,
+}
+
+impl ScanScope {
 
-        let mut tables: HashMap<String, ProjectDatabaseTable> = HashMap::new();
-        let mut table =  ProjectDatabaseTable {
-            columns: HashMap::new(),
-            indexes: None
+    fn all() -> Self {
+        ScanScope { schemas: None, tables: None }
+    }
+
+    fn contains(&self, schema_name: &str, table_name: &str) -> bool {
+        let contains_schema = match &self.schemas {
+            Some(schemas) => schemas.iter().any(|schema| schema == schema_name),
+            None => true,
         };
-        let mut table_name = String::from("");
-
-        let result = db::scan_tables_and_columns(
-            database.connection.clone(),
-            config.configurations.clone(),
-            database.configurations.clone()
-        ).await?;
-
-        for column_info in result {
-            let current_table_name = format!("{}___{}", column_info.schema_name, column_info.table_name);
-            if current_table_name != table_name {
-                if !table_name.is_empty() {
-                    tables.insert(table_name, ProjectDatabaseTable {
-                        columns: table.columns,
-                        indexes: None
-                    });
-                }
 
-                table_name = current_table_name;
-                table.columns = HashMap::new();
+        let contains_table = match &self.tables {
+            Some(tables) => tables.iter().any(|table| table.is_match(table_name)),
+            None => true,
+        };
+
+        contains_schema && contains_table
+    }
+
+    /// Same as `contains`, for the tables of a scan store (`{schema_name}___{table_name}`)
+    fn contains_store_name(&self, name: &str) -> bool {
+        match key::parse_store_name(name) {
+            Some((schema_name, table_name)) => self.contains(&schema_name, &table_name),
+            None => self.contains(name, ""),
+        }
+    }
+
+}
+
+/// A `--table` pattern, which must match the whole table name (`user` does not match `user_roles`)
+fn parse_table_pattern(table: &str) -> Result<Regex> {
+    Regex::new(&format!("^(?:{})$", table))
+        .map_err(|err| anyhow!("The table pattern '{}' is not a valid regex: {}", table, err))
+}
+
+/// Compares the current fingerprint of each schema with the one stored by the previous scan and
+/// returns the schemas that changed (or disappeared), or `None` when the database can be skipped.
+async fn get_schemas_to_scan(
+    database_name: &String,
+    database: &mut ProjectDatabase,
+    configurations: &Option<ProjectConfiguration>,
+    full: bool,
+) -> Result<Option<ScanScope>> {
+    log::debug(format!("Fingerprinting database {} at {}", database_name, database.get_connection()));
+    let phase = Phase::start(format!("Fingerprinting database {}", database_name));
+
+    let fingerprints: BTreeMap<String, String> = db::scan_fingerprints(
+        database.get_connection().clone(),
+        configurations.clone(),
+        database.configurations.clone()
+    ).await?
+        .into_iter()
+        .map(|schema| (schema.schema_name, schema.fingerprint))
+        .collect();
+    phase.finish();
+
+    let previous_fingerprints = database.fingerprints.replace(fingerprints.clone());
+    let previous_fingerprints = match previous_fingerprints {
+        Some(previous_fingerprints) if !full && database.tables.is_some() => previous_fingerprints,
+        _ => return Ok(Some(ScanScope::all())),
+    };
+
+    for schema_name in previous_fingerprints.keys() {
+        if !fingerprints.contains_key(schema_name) {
+            log::info(format!("Schema {} no longer exists in database {}", schema_name, database_name));
+        }
+    }
+
+    let changed_schemas = get_changed_schemas(&previous_fingerprints, &fingerprints);
+    if changed_schemas.is_empty() {
+        log::info(format!("No schema changed in database {} since the last scan, skipping it", database_name));
+        return Ok(None);
+    }
+
+    log::info(format!("Schemas changed in database {} since the last scan: {}", database_name, changed_schemas.join(", ")));
+    Ok(Some(ScanScope { schemas: Some(changed_schemas), tables: None }))
+}
+
+/// The schemas whose fingerprint is new, differs from the previous one or disappeared, sorted by name
+fn get_changed_schemas(previous_fingerprints: &BTreeMap<String, String>, fingerprints: &BTreeMap<String, String>) -> Vec<String> {
+    let mut changed_schemas: Vec<String> = fingerprints.iter()
+        .filter(|(schema_name, fingerprint)| previous_fingerprints.get(*schema_name) != Some(fingerprint))
+        .map(|(schema_name, _)| schema_name.to_string())
+        .chain(previous_fingerprints.keys()
+            .filter(|schema_name| !fingerprints.contains_key(*schema_name))
+            .cloned())
+        .collect();
+
+    changed_schemas.sort();
+    changed_schemas
+}
+
+async fn scan_tables_and_columns(
+    database_name: &String,
+    database: &mut ProjectDatabase,
+    configurations: &Option<ProjectConfiguration>,
+    scope: &ScanScope,
+    database_scan: &mut DatabaseScan,
+) -> Result<()> {
+    log::info(format!("Scanning database {} at {}", database_name, database.get_connection()));
+    let phase = Phase::start(format!("Scanning the columns of database {}", database_name));
+
+    let (mut previous_tables, mut tables): (BTreeMap<String, ProjectDatabaseTable>, BTreeMap<String, ProjectDatabaseTable>) = database.tables
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|(table_name, _)| scope.contains_store_name(table_name));
+
+    let mut scanned_tables: BTreeMap<(String, String), ProjectDatabaseTable> = BTreeMap::new();
+    let progress = Progress::start(format!("Scanning database {}", database_name));
+    db::scan_tables_and_columns(
+        database.get_connection().clone(),
+        configurations.clone(),
+        database.configurations.clone(),
+        scope.schemas.clone(),
+        |column_info| {
+            progress.inc();
+            if !scope.contains(&column_info.schema_name, &column_info.table_name) {
+                return;
             }
 
-            let project_database_column = ProjectDatabaseColumn { 
-                data_type: column_info.data_type, 
-                data_precision: column_info.data_precision.map(|x| x.to_string()), 
+            let table = scanned_tables
+                .entry((column_info.schema_name, column_info.table_name))
+                .or_insert_with(|| ProjectDatabaseTable {
+                    columns: BTreeMap::new(),
+                    indexes: None,
+                    annotations: None,
+                });
+
+            table.columns.insert(column_info.column_name, ProjectDatabaseColumn {
+                data_type: column_info.data_type,
+                data_precision: column_info.data_precision.map(|x| x.to_string()),
                 is_primary_key: column_info.is_primary_key,
-                is_nullable: column_info.is_nullable, 
-                is_unique: column_info.is_unique, 
+                is_nullable: column_info.is_nullable,
+                is_unique: column_info.is_unique,
                 is_auto_increment: column_info.is_auto_increment,
                 default_value: column_info.default_value,
-                ordinal_position: column_info.ordinal_position
-            };
-
-            table.columns.insert(column_info.column_name, project_database_column);
+                ordinal_position: column_info.ordinal_position,
+                annotations: None,
+            });
         }
+    ).await?;
+    database_scan.rows = progress.finish();
+    database_scan.tables = scanned_tables.len();
+    database_scan.columns = scanned_tables.values().map(|table| table.columns.len()).sum();
+    log::debug(format!("Read {} rows from database {} in {}", database_scan.rows, database_name, phase.elapsed()));
 
-        tables.insert(table_name, ProjectDatabaseTable {
-            columns: table.columns,
-            indexes: None
-        });
+    for ((schema_name, table_name), mut table) in scanned_tables {
+        let table_key = TableKey::new(database_name, &schema_name, &table_name);
+        let table_name = table_key.get_store_name();
+        if let Some(previous_table) = previous_tables.remove(&table_name) {
+            for column_name in table.merge_annotations(previous_table) {
+                log::warn(format!("Column {} was dropped from table {} of database {}", column_name, table_name, database_name));
+                database_scan.dropped_columns.push(table_key.column(&column_name).to_string());
+            }
+        }
+        tables.insert(table_name, table);
+    }
 
-        database.tables = Some(tables);
-        println!("Finished scanning!")
+    let mut dropped_tables: Vec<(String, ProjectDatabaseTable)> = previous_tables.into_iter().collect();
+    dropped_tables.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (table_name, table) in dropped_tables {
+        match table.annotations {
+            Some(_) => log::warn(format!("Table {} was dropped from database {} (its annotations were discarded)", table_name, database_name)),
+            None => log::info(format!("Table {} was dropped from database {}", table_name, database_name)),
+        }
+        database_scan.dropped_tables.push(key::format_table_key(database_name, &table_name));
     }
 
+    database.tables = Some(tables);
+    phase.finish();
+
     Ok(())
 }
 
-fn search_for_composite_primary_keys(config: &mut Config) {
-    println!("Searching for composite primary keys...");
-    for (_, database) in &mut config.databases {
+fn search_for_composite_primary_keys(config: &mut Config) -> Vec<CompositePrimaryKey> {
+    let phase = Phase::start("Searching for composite primary keys");
+    let mut composite_primary_keys: Vec<CompositePrimaryKey> = Vec::new();
+    for (database_name, database) in &mut config.databases {
         if let Some(tables) = &mut database.tables {
             for (table_name, table) in tables {
 
                 let mut primary_keys: Vec<String> = Vec::new();
 
-                for (column_name, column) in &table.columns {
+                for (column_name, column) in table.get_ordered_columns() {
                     if column.is_primary_key {
                         primary_keys.push(column_name.to_string());
                     }
                 }
 
                 if primary_keys.len() > 1 {
-                    println!("Table {} has a composite primary key: {}", table_name, primary_keys.join(", "));
+                    log::debug(format!("Table {} has a composite primary key: {}", table_name, primary_keys.join(", ")));
+                    composite_primary_keys.push(CompositePrimaryKey {
+                        table_key: key::format_table_key(database_name, table_name),
+                        columns: primary_keys.clone(),
+                    });
 
                     let index = ProjectDatabaseIndex {
                         columns: primary_keys,
                         is_primary_key: true,
                     };
-                    let mut indexes: Vec<ProjectDatabaseIndex> = Vec::new();
-                    indexes.push(index);
-                    table.indexes = Some(indexes);
+                    table.indexes = Some(vec![index]);
 
-                    for (_, column) in &mut table.columns {
+                    for column in table.columns.values_mut() {
                         if column.is_primary_key {
                             column.is_primary_key = false;
                         }
@@ -114,52 +367,99 @@ fn search_for_composite_primary_keys(config: &mut Config) {
             }
         }
     }
-    println!("Finished searching for composite primary keys!");
+    phase.finish();
+    composite_primary_keys
 }
 
-async fn scan_references(config: &mut Config) -> Result<()> {
-    for (database_name, database) in &mut config.databases {
-        println!("Scanning references from {} at {}", database_name, database.connection.get_connection_string());
-
-        let result = db::scan_references(
-            database.connection.clone(),
-            config.configurations.clone(),
-            database.configurations.clone()
-        ).await?;
-        for reference_info in result {
-            let key = format!(
-                "{}___{}___{}.{}",
-                database_name,
-                reference_info.schema_name,
-                reference_info.table_name,
-                reference_info.column_name
-            );
-            let referenced_key = format!(
-                "{}___{}___{}.{}", 
-                database_name,
-                reference_info.referenced_schema_name,
-                reference_info.referenced_table_name,
-                reference_info.referenced_column_name
-            );
-
-            match config.references {
-                Some(ref mut map) => {
-                    map.entry(key.clone())
-                        .or_insert_with(Vec::new)
-                        .push(referenced_key);
-                }
-                None => {
-                    let mut new_map = HashMap::new();
-                    let mut new_vec = Vec::new();
-                    new_vec.push(referenced_key);
-                    new_map.insert(key.clone(), new_vec);
-                    config.references = Some(new_map);
-                }
-            }
+async fn scan_references(
+    database_name: &String,
+    database: &ProjectDatabase,
+    configurations: &Option<ProjectConfiguration>,
+    references: &mut Option<BTreeMap<String, Vec<String>>>,
+    scope: &ScanScope,
+    database_scan: &mut DatabaseScan,
+) -> Result<()> {
+    log::debug(format!("Scanning references from {} at {}", database_name, database.get_connection()));
+    let phase = Phase::start(format!("Scanning the references of database {}", database_name));
+
+    let map = references.get_or_insert_with(BTreeMap::new);
+    map.retain(|key, _| match ColumnKey::parse(key) {
+        Ok(key) if key.table.database_name == *database_name => !scope.contains(&key.table.schema_name, &key.table.table_name),
+        _ => true,
+    });
+
+    let result = db::scan_references(
+        database.get_connection().clone(),
+        configurations.clone(),
+        database.configurations.clone(),
+        scope.schemas.clone()
+    ).await?;
+    let mut scanned_keys: BTreeSet<String> = BTreeSet::new();
+    for reference_info in result {
+        if !scope.contains(&reference_info.schema_name, &reference_info.table_name) {
+            continue;
         }
 
-        println!("Finished scanning references!")
+        let key = TableKey::new(database_name, &reference_info.schema_name, &reference_info.table_name)
+            .column(&reference_info.column_name);
+        let referenced_key = TableKey::new(database_name, &reference_info.referenced_schema_name, &reference_info.referenced_table_name)
+            .column(&reference_info.referenced_column_name);
+
+        map.entry(key.to_string())
+            .or_default()
+            .push(referenced_key.to_string());
+        scanned_keys.insert(key.to_string());
+    }
+
+    for referenced_keys in map.values_mut() {
+        referenced_keys.sort();
+        referenced_keys.dedup();
     }
+    // Counted after the dedup, as the same reference can be returned more than once
+    database_scan.references += scanned_keys.iter()
+        .filter_map(|key| map.get(key))
+        .map(Vec::len)
+        .sum::<usize>();
 
+    if map.is_empty() {
+        *references = None;
+    }
+
+    phase.finish();
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeMap;
+
+    use super::{get_changed_schemas, parse_table_pattern};
+
+    fn fingerprints(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries.iter().map(|(schema_name, fingerprint)| (schema_name.to_string(), fingerprint.to_string())).collect()
+    }
+
+    #[test]
+    fn test_get_changed_schemas() {
+        let previous = fingerprints(&[("billing", "a"), ("main", "b"), ("old", "c")]);
+        let current = fingerprints(&[("billing", "a"), ("main", "x"), ("new", "d")]);
+        assert_eq!(get_changed_schemas(&previous, &current), vec!["main", "new", "old"]);
+        assert!(get_changed_schemas(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_table_patterns_match_whole_names() {
+        let pattern = parse_table_pattern("user").unwrap();
+        assert!(pattern.is_match("user"));
+        assert!(!pattern.is_match("user_roles"));
+        assert!(!pattern.is_match("audit_users"));
+
+        let pattern = parse_table_pattern("user|order_.*").unwrap();
+        assert!(pattern.is_match("order_items"));
+        assert!(!pattern.is_match("user_roles"));
+
+        assert!(parse_table_pattern("(").is_err());
+    }
+
+}
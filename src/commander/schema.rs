@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use crate::{config, log, output::{self, Output}, schema};
+
+use super::Command;
+
+pub struct SchemaCommand {
+    pub store: bool,
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct SchemaResult {
+    path: PathBuf,
+}
+
+impl Output for SchemaResult {
+
+    fn print_text(&self) {
+        println!("JSON Schema written to {}", self.path.display());
+    }
+
+}
+
+impl Command for SchemaCommand {
+
+    fn get_starting_message(&self) -> String {
+        match self.store {
+            true => String::from("Generating the JSON Schema of the scan store files"),
+            false => String::from("Generating the JSON Schema of the project files"),
+        }
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let (schema, file_name) = match self.store {
+            true => (schema::get_store_schema(), "dbml-store.schema.json"),
+            false => (schema::get_project_schema(), "dbml-project.schema.json"),
+        };
+
+        let path = match &self.output {
+            Some(output) => output.clone(),
+            None => config::get_config_dir()?.join(file_name),
+        };
+
+        let contents = serde_json::to_string_pretty(&schema)
+            .map_err(|err| anyhow!(format!("Could not generate the JSON Schema: {}", err)))?;
+        config::write_file(&path, &(contents + "\n"))
+            .map_err(|err| anyhow!(format!("Could not write the JSON Schema to '{}': {}", path.display(), err)))?;
+
+        if !self.store {
+            log::info("Editors using the YAML language server can pick it up by starting the project files with:");
+            log::info(format!("# yaml-language-server: $schema={}", path.display()));
+        }
+
+        output::print(&SchemaResult { path })
+    }
+
+}
@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use anyhow::{anyhow, Result};
 use regex::Regex;
-use crate::config::{self, Config};
+use serde::Serialize;
+use crate::{config::{self, Config}, error::Error, key::{ColumnKey, TableKey}, log, output::{self, Output}, workspace::{self, Workspace}};
 
 use super::Command;
 
@@ -10,74 +11,93 @@ pub struct SearchCommand {
     pub project: String,
     pub regex: String,
     pub referenced_key: Option<String>,
+    pub workspace: bool,
 }
 
-impl Command for SearchCommand {
+#[derive(Serialize)]
+struct SearchResult {
+    regex: String,
+    /// The key the found columns were set to reference, as custom references
+    referenced_key: Option<String>,
+    columns: Vec<FoundColumn>,
+}
 
-    async fn execute(&self) -> Result<()> {
-        println!("Searching for columns that match '{}' in the '{}' project", self.regex, self.project);
+/// An unmapped column (neither in `references` nor in `custom_references`) whose name matches the regex
+#[derive(Serialize)]
+struct FoundColumn {
+    key: String,
+    database_name: String,
+    schema_name: String,
+    table_name: String,
+    column_name: String,
+}
 
-        let mut config: Config = config::load(&self.project)?;
-        let column_name_regex = Regex::new(&self.regex).unwrap();
-        let referenced_key_regex = Regex::new(r"^(.+)___(.+)\.(.+)$").unwrap();
-        let mut referenced_database_name = "";
-        let mut referenced_table_name = "";
-        let mut referenced_column_name = "";
-        let mut should_add_references = false;
+impl Output for SearchResult {
 
-        let table_name_joined;
-        if let Some(referenced_key) = &self.referenced_key {
-            if !referenced_key_regex.is_match(self.referenced_key.as_ref().unwrap()) {
-                return Err(anyhow!("The referenced key must follow this pattern: DATABASE_NAME___TABLE_NAME.COLUMN_NAME"));
-            }
+    fn print_text(&self) {
+        for (index, column) in self.columns.iter().enumerate() {
+            println!("{}: Found an unmapped column matching '{}': {} ({})", index + 1, self.regex, column.column_name, TableKey::new(&column.database_name, &column.schema_name, &column.table_name));
+        }
+    }
 
-            let parts_by_period: Vec<&str> = referenced_key.split(".").collect();
-            referenced_column_name = parts_by_period[1];
-            let parts_by_underscore: Vec<&str> = parts_by_period[0].split("___").collect();
-            referenced_database_name = parts_by_underscore[0];
-            table_name_joined = parts_by_underscore[1..].join("___");
-            referenced_table_name = &table_name_joined;
+}
 
-            if !config.databases.contains_key(referenced_database_name)
-            || !config.databases.get(referenced_database_name).unwrap().tables.clone().unwrap().contains_key(referenced_table_name)
-            || !config.databases.get(referenced_database_name).unwrap().tables.clone().unwrap().get(referenced_table_name).unwrap().columns.contains_key(referenced_column_name) {
-                return Err(anyhow!("The given refereced key '{}' does not match a mapped column in the config file for project {}", self.referenced_key.clone().unwrap(), self.project));
-            }
+impl Command for SearchCommand {
 
-            should_add_references = true;
-        }
+    async fn execute(&self) -> Result<()> {
+        let mut workspace: Option<Workspace> = match self.workspace {
+            true => Some(workspace::load(&self.project)?),
+            false => None,
+        };
+        let mut config: Config = match &workspace {
+            Some(workspace) => workspace.to_config(),
+            None => config::load(&self.project)?,
+        };
+        let column_name_regex = Regex::new(&self.regex)
+            .map_err(|err| anyhow!("The column name pattern '{}' is not a valid regex: {}", self.regex, err))?;
+
+        let referenced_key = match &self.referenced_key {
+            Some(referenced_key) => {
+                let referenced_key = ColumnKey::parse(referenced_key)?;
+                if config.get_column(&referenced_key.to_string()).is_none() {
+                    return Err(Error::InvalidKey(format!("The given refereced key '{}' does not match a mapped column in the config file for project {}", referenced_key, self.project)).into());
+                }
+                Some(referenced_key.to_string())
+            }
+            None => None,
+        };
 
-        let mut found = 0;
+        let mut columns: Vec<FoundColumn> = Vec::new();
         for (database_name, database) in &mut config.databases {
             if let Some(tables) = &mut database.tables {
-                println!("Searching on {} tables...", database_name);
-                for (table_name, table) in tables {    
-                    for (column_name, _) in &table.columns {
+                log::info(format!("Searching on {} tables...", database_name));
+                for (table_name, table) in tables {
+                    let table_key = match TableKey::from_store(database_name, table_name) {
+                        Some(table_key) => table_key,
+                        None => {
+                            log::warn(format!("Table {} of database {} is not named {{schema_name}}___{{table_name}}, skipping it", table_name, database_name));
+                            continue;
+                        }
+                    };
+                    for column_name in table.columns.keys() {
                         if column_name_regex.is_match(column_name) {
-                            let key = format!("{}___{}.{}", database_name, table_name, column_name);
+                            let key = table_key.column(column_name).to_string();
                             if (config.references.is_none() 
                             || !config.references.as_ref().unwrap().contains_key(&key))
                             && (config.custom_references.is_none()
                             || !config.custom_references.clone().unwrap().contains_key(&key)) {
-                                found = found + 1;
-                                println!("{}: {}", found, format!("Found an unmapped column matching '{}': {} ({}___{})", self.regex, column_name, database_name, table_name));
-                                if should_add_references {
-                                    
-                                    let referenced_key = format!("{}___{}.{}", referenced_database_name, referenced_table_name, referenced_column_name);
-                                    match config.custom_references {
-                                        Some(ref mut map) => {
-                                            map.entry(key.clone())
-                                                .or_insert_with(Vec::new)
-                                                .push(referenced_key);
-                                        }
-                                        None => {
-                                            let mut new_map = HashMap::new();
-                                            let mut new_vec = Vec::new();
-                                            new_vec.push(referenced_key);
-                                            new_map.insert(key.clone(), new_vec);
-                                            config.custom_references = Some(new_map);
-                                        }
-                                    }
+                                columns.push(FoundColumn {
+                                    key: key.clone(),
+                                    database_name: database_name.to_string(),
+                                    schema_name: table_key.schema_name.to_string(),
+                                    table_name: table_key.table_name.to_string(),
+                                    column_name: column_name.to_string(),
+                                });
+                                if let Some(referenced_key) = &referenced_key {
+                                    config.custom_references.get_or_insert_with(BTreeMap::new)
+                                        .entry(key.clone())
+                                        .or_default()
+                                        .push(referenced_key.to_string());
                                 }
                             }
                         }
@@ -86,13 +106,25 @@ impl Command for SearchCommand {
             }
         }
 
-        config.save()?;
-        
-        Ok(())
+        if referenced_key.is_some() && !columns.is_empty() {
+            match &mut workspace {
+                Some(workspace) => workspace.save(&config)?,
+                None => config.save()?,
+            }
+        }
+
+        output::print(&SearchResult {
+            regex: self.regex.to_string(),
+            referenced_key,
+            columns,
+        })
     }
     
     fn get_starting_message(&self) -> String {
-        format!("Looking for unmapped columns in the {} project that match {}", self.project, self.regex)
+        match self.workspace {
+            true => format!("Looking for unmapped columns in the {} workspace that match {}", self.project, self.regex),
+            false => format!("Looking for unmapped columns in the {} project that match {}", self.project, self.regex),
+        }
     }
 
 }
\ No newline at end of file
@@ -0,0 +1,325 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use crate::{config::{self, Config, FileConfig}, error::Error, log, output::{self, Output}, schema};
+
+use super::Command;
+
+pub struct ConfigGetCommand {
+    pub project: String,
+    pub path: String,
+}
+
+pub struct ConfigSetCommand {
+    pub project: String,
+    pub path: String,
+    pub value: String,
+    pub append: bool,
+}
+
+pub struct ConfigUnsetCommand {
+    pub project: String,
+    pub path: String,
+}
+
+#[derive(Serialize)]
+struct GetResult {
+    path: String,
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct SetResult {
+    path: String,
+    /// The value as it was written (after being read as YAML)
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct UnsetResult {
+    path: String,
+    /// Whether the setting was set before
+    removed: bool,
+    /// The databases removed with the setting
+    removed_databases: Vec<String>,
+}
+
+impl Output for GetResult {
+
+    fn print_text(&self) {
+        match &self.value {
+            Value::String(value) => println!("{}", value),
+            value => print!("{}", serde_yaml::to_string(value).unwrap_or_default()),
+        }
+    }
+
+}
+
+impl Output for SetResult {
+
+    fn print_text(&self) {
+        println!("'{}' set", self.path);
+    }
+
+}
+
+impl Output for UnsetResult {
+
+    fn print_text(&self) {
+        match self.removed {
+            true => println!("'{}' unset", self.path),
+            false => println!("'{}' is not set", self.path),
+        }
+    }
+
+}
+
+impl Command for ConfigGetCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Reading '{}' from the config file of the '{}' project", self.path, self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let config: Config = config::load(&self.project)?;
+        let settings = to_value(&config)?;
+        let path = parse_path(&self.path)?;
+
+        let value = get(&settings, &path)
+            .ok_or(Error::InvalidKey(format!("'{}' is not set in the config file of the '{}' project", self.path, self.project)))?;
+
+        output::print(&GetResult { path: self.path.to_string(), value: value.clone() })
+    }
+
+}
+
+impl Command for ConfigSetCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Setting '{}' in the config file of the '{}' project", self.path, self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut config: Config = config::load(&self.project)?;
+        let path = parse_path(&self.path)?;
+
+        // The value is read as YAML (so `3307` is a number and `[a, b]` a list), unless the field
+        // expects a string: a password like `1234` is taken as it is
+        let value: Value = serde_yaml::from_str(&self.value).unwrap_or(Value::String(self.value.to_string()));
+        let mut candidates = vec![value.clone()];
+        if !matches!(value, Value::String(_) | Value::Mapping(_) | Value::Sequence(_)) {
+            candidates.push(Value::String(self.value.to_string()));
+        }
+
+        let mut error: Option<anyhow::Error> = None;
+        for candidate in candidates {
+            let mut settings = to_value(&config)?;
+            set(&mut settings, &path, candidate, self.append)?;
+            let value = get(&settings, &path).cloned().unwrap_or_default();
+
+            match from_value(settings) {
+                Ok(file_config) => {
+                    config.set_file_config(file_config);
+                    config.save()?;
+                    return output::print(&SetResult { path: self.path.to_string(), value });
+                }
+                Err(err) => { error.get_or_insert(err); }
+            }
+        }
+
+        Err(anyhow!(format!("Could not set '{}': {}", self.path, error.map(|err| err.to_string()).unwrap_or_default())))
+    }
+
+}
+
+impl Command for ConfigUnsetCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Unsetting '{}' in the config file of the '{}' project", self.path, self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut config: Config = config::load(&self.project)?;
+        let path = parse_path(&self.path)?;
+
+        let mut settings = to_value(&config)?;
+        if !remove(&mut settings, &path) {
+            return output::print(&UnsetResult { path: self.path.to_string(), removed: false, removed_databases: Vec::new() });
+        }
+
+        let file_config = from_value(settings)
+            .map_err(|err| anyhow!(format!("Could not unset '{}': {}", self.path, err)))?;
+
+        let removed_databases: Vec<String> = config.databases.keys()
+            .filter(|database_name| !file_config.databases.contains_key(*database_name))
+            .map(String::to_string)
+            .collect();
+        for database_name in &removed_databases {
+            log::warn(format!("The database {} was removed (its scan store was kept)", database_name));
+        }
+
+        config.set_file_config(file_config);
+        config.save()?;
+        output::print(&UnsetResult { path: self.path.to_string(), removed: true, removed_databases })
+    }
+
+}
+
+fn to_value(config: &Config) -> Result<Value> {
+    serde_yaml::to_value(config.to_file_config())
+        .map_err(|err| anyhow!(format!("Could not read the config file of the '{}' project: {}", config.project, err)))
+}
+
+/// Checks the edited settings against the types of the project file, explaining where they do not
+/// match with the JSON Schema of the project files (serde's errors do not say which field failed)
+fn from_value(settings: Value) -> Result<FileConfig> {
+    let violations = schema::validate("", &settings)?;
+    if let Some(violation) = violations.first() {
+        return Err(anyhow!(format!("{}: {}", violation.path, violation.message)));
+    }
+
+    serde_yaml::from_value::<FileConfig>(settings).map_err(|err| anyhow!(err.to_string()))
+}
+
+/// Splits a path like `databases.pay.connection.port` into its keys. Keys that contain dots (such
+/// as column keys in `custom_references`) are written between double quotes.
+fn parse_path(path: &str) -> Result<Vec<String>> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut key = String::new();
+    let mut quoted = false;
+
+    for character in path.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            '.' if !quoted => keys.push(std::mem::take(&mut key)),
+            _ => key.push(character),
+        }
+    }
+    keys.push(key);
+
+    if quoted || keys.iter().any(String::is_empty) {
+        return Err(anyhow!("'{}' is not a valid path (keys are separated by dots, and keys with dots are written between double quotes)", path));
+    }
+    if keys[0] == "version" {
+        return Err(anyhow!("The version of the config file is managed by dbml (see 'dbml migrate')"));
+    }
+
+    Ok(keys)
+}
+
+fn get<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Sequence(items) => items.get(key.parse::<usize>().ok()?),
+        value => value.get(key.as_str()),
+    })
+}
+
+/// Sets the value at the path, creating the mappings leading to it. With `append`, the value is
+/// added to the list at the path instead.
+fn set(value: &mut Value, path: &[String], new_value: Value, append: bool) -> Result<()> {
+    let (key, rest) = match path.split_first() {
+        Some(parts) => parts,
+        None if append => {
+            match value {
+                Value::Sequence(items) => items.push(new_value),
+                Value::Null => *value = Value::Sequence(vec![new_value]),
+                _ => return Err(anyhow!("The value to append to is not a list")),
+            }
+            return Ok(());
+        }
+        None => {
+            *value = new_value;
+            return Ok(());
+        }
+    };
+
+    if value.is_null() {
+        *value = Value::Mapping(Mapping::new());
+    }
+
+    let child = match value {
+        Value::Mapping(mapping) => mapping.entry(Value::String(key.to_string())).or_insert(Value::Null),
+        Value::Sequence(items) => key.parse::<usize>().ok()
+            .and_then(|index| items.get_mut(index))
+            .ok_or(anyhow!("'{}' is not an index of the list", key))?,
+        _ => return Err(anyhow!("'{}' cannot be set: its parent is not a mapping or a list", key)),
+    };
+
+    set(child, rest, new_value, append)
+}
+
+/// Removes the value at the path, returning whether it existed
+fn remove(value: &mut Value, path: &[String]) -> bool {
+    let (key, rest) = match path.split_first() {
+        Some(parts) => parts,
+        None => return false,
+    };
+
+    if !rest.is_empty() {
+        let child = match value {
+            Value::Sequence(items) => key.parse::<usize>().ok().and_then(|index| items.get_mut(index)),
+            value => value.get_mut(key.as_str()),
+        };
+        return child.is_some_and(|child| remove(child, rest));
+    }
+
+    match value {
+        Value::Mapping(mapping) => mapping.remove(key.as_str()).is_some(),
+        Value::Sequence(items) => match key.parse::<usize>() {
+            Ok(index) if index < items.len() => {
+                items.remove(index);
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{env, fs};
+
+    use serde_yaml::Value;
+
+    use crate::{commander::Command, config, error};
+
+    use super::{get, parse_path, remove, set, ConfigGetCommand};
+
+    #[test]
+    fn test_paths() {
+        let mut value: Value = serde_yaml::from_str("databases:\n  pay:\n    connection:\n      port: 3306\n").unwrap();
+
+        let port = parse_path("databases.pay.connection.port").unwrap();
+        set(&mut value, &port, Value::from(3307), false).unwrap();
+        assert_eq!(get(&value, &port), Some(&Value::from(3307)));
+
+        let schemas = parse_path("databases.pay.configurations.schemas_to_ignore").unwrap();
+        set(&mut value, &schemas, Value::from("sys"), true).unwrap();
+        set(&mut value, &schemas, Value::from("mysql"), true).unwrap();
+        assert_eq!(get(&value, &parse_path("databases.pay.configurations.schemas_to_ignore.1").unwrap()), Some(&Value::from("mysql")));
+
+        let custom_reference = parse_path("custom_references.\"pay___main___orders.user_id\"").unwrap();
+        assert_eq!(custom_reference[1], "pay___main___orders.user_id");
+        set(&mut value, &custom_reference, Value::from("pay___main___users.id"), true).unwrap();
+
+        assert!(remove(&mut value, &schemas));
+        assert!(!remove(&mut value, &schemas));
+        assert!(parse_path("databases..port").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_a_setting_that_is_not_set() {
+        let directory = env::temp_dir().join("dbml_test_config_get_not_set");
+        fs::create_dir_all(&directory).unwrap();
+        config::set_test_config_dir(directory.clone());
+        fs::write(directory.join("shop.yaml"), "version: 2\ndatabases: {}\n").unwrap();
+
+        let command = ConfigGetCommand { project: String::from("shop"), path: String::from("custom_references") };
+        let error = command.execute().await.unwrap_err();
+
+        assert_eq!(error::get_exit_code(&error), 7);
+    }
+
+}
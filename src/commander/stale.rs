@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use crate::{config::{self, Config}, key::{ColumnKey, TableKey}, log, output::{self, Output}};
+
+use super::Command;
+
+pub struct StaleCommand {
+    pub project: String,
+    pub prune: bool,
+    pub rewrite: bool,
+}
+
+#[derive(Serialize)]
+struct StaleResult {
+    stale_references: Vec<StaleReference>,
+    changes: Vec<StaleReferenceChange>,
+}
+
+#[derive(Serialize)]
+struct StaleReference {
+    key: String,
+    referenced_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_suggestions: Option<Vec<Suggestion>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referenced_key_suggestions: Option<Vec<Suggestion>>,
+}
+
+/// A scanned column that may be what a missing key was renamed to
+#[derive(Serialize)]
+struct Suggestion {
+    key: String,
+    distance: usize,
+}
+
+/// What `--prune` or `--rewrite` did to a stale reference
+#[derive(Serialize)]
+struct StaleReferenceChange {
+    /// `rewritten`, `kept` (when there is no single candidate to rewrite it with) or `pruned`
+    action: &'static str,
+    key: String,
+    referenced_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_referenced_key: Option<String>,
+}
+
+impl StaleReferenceChange {
+
+    fn new(action: &'static str, stale_reference: &StaleReference) -> Self {
+        StaleReferenceChange {
+            action,
+            key: stale_reference.key.to_string(),
+            referenced_key: stale_reference.referenced_key.to_string(),
+            new_key: None,
+            new_referenced_key: None,
+        }
+    }
+
+}
+
+impl Output for StaleResult {
+
+    fn print_text(&self) {
+        if self.stale_references.is_empty() {
+            println!("No stale custom references found");
+            return;
+        }
+
+        for stale_reference in &self.stale_references {
+            println!("Stale custom reference: {} - {}", stale_reference.key, stale_reference.referenced_key);
+            print_suggestions(&stale_reference.key, &stale_reference.key_suggestions);
+            print_suggestions(&stale_reference.referenced_key, &stale_reference.referenced_key_suggestions);
+        }
+    }
+
+}
+
+impl Command for StaleCommand {
+
+    fn get_starting_message(&self) -> String {
+        format!("Looking for stale custom references in the '{}' project", self.project)
+    }
+
+    async fn execute(&self) -> Result<()> {
+        let mut config: Config = config::load(&self.project)?;
+        let mut result = StaleResult {
+            stale_references: find_stale_references(&config),
+            changes: Vec::new(),
+        };
+
+        if result.stale_references.is_empty() || (!self.prune && !self.rewrite) {
+            return output::print(&result);
+        }
+
+        let custom_references = config.custom_references.get_or_insert_with(BTreeMap::new);
+        for stale_reference in &result.stale_references {
+            if self.rewrite {
+                let key = get_rewritten_key(&stale_reference.key, &stale_reference.key_suggestions);
+                let referenced_key = get_rewritten_key(&stale_reference.referenced_key, &stale_reference.referenced_key_suggestions);
+
+                if let (Some(key), Some(referenced_key)) = (key, referenced_key) {
+                    log::info(format!("Rewriting {} - {} as {} - {}", stale_reference.key, stale_reference.referenced_key, key, referenced_key));
+                    remove_custom_reference(custom_references, &stale_reference.key, &stale_reference.referenced_key);
+                    let referenced_keys = custom_references.entry(key.to_string()).or_default();
+                    if !referenced_keys.contains(&referenced_key) {
+                        referenced_keys.push(referenced_key.to_string());
+                    }
+
+                    let mut change = StaleReferenceChange::new("rewritten", stale_reference);
+                    change.new_key = Some(key);
+                    change.new_referenced_key = Some(referenced_key);
+                    result.changes.push(change);
+                    continue;
+                }
+
+                if !self.prune {
+                    log::info(format!("Keeping {} - {}: there is no single candidate to rewrite it with", stale_reference.key, stale_reference.referenced_key));
+                    result.changes.push(StaleReferenceChange::new("kept", stale_reference));
+                    continue;
+                }
+            }
+
+            log::info(format!("Pruning {} - {}", stale_reference.key, stale_reference.referenced_key));
+            remove_custom_reference(custom_references, &stale_reference.key, &stale_reference.referenced_key);
+            result.changes.push(StaleReferenceChange::new("pruned", stale_reference));
+        }
+
+        if custom_references.is_empty() {
+            config.custom_references = None;
+        }
+
+        // Keeping every stale reference leaves the project file as it is, and out of the history
+        if result.changes.iter().any(|change| change.action != "kept") {
+            config.save()?;
+        }
+        output::print(&result)
+    }
+
+}
+
+fn find_stale_references(config: &Config) -> Vec<StaleReference> {
+    let mut stale_references: Vec<StaleReference> = Vec::new();
+
+    if let Some(custom_references) = &config.custom_references {
+        for (key, referenced_keys) in custom_references {
+            for referenced_key in referenced_keys {
+                let key_exists = config.get_column(key).is_some();
+                let referenced_key_exists = config.get_column(referenced_key).is_some();
+                if key_exists && referenced_key_exists {
+                    continue;
+                }
+
+                stale_references.push(StaleReference {
+                    key: key.to_string(),
+                    referenced_key: referenced_key.to_string(),
+                    key_suggestions: (!key_exists).then(|| get_suggestions(config, key)),
+                    referenced_key_suggestions: (!referenced_key_exists).then(|| get_suggestions(config, referenced_key)),
+                });
+            }
+        }
+    }
+
+    stale_references.sort_by(|a, b| (&a.key, &a.referenced_key).cmp(&(&b.key, &b.referenced_key)));
+    stale_references
+}
+
+/// Lists (closest first) the scanned columns of the key's database whose key is close to the
+/// missing one, which is how a renamed column or a table moved to another schema usually shows up.
+fn get_suggestions(config: &Config, key: &str) -> Vec<Suggestion> {
+    let missing = match ColumnKey::parse(key) {
+        Ok(missing) => missing,
+        Err(_) => return Vec::new(),
+    };
+    let database_name = &missing.table.database_name;
+
+    let tables = match config.databases.get(database_name).and_then(|database| database.tables.as_ref()) {
+        Some(tables) => tables,
+        None => return Vec::new(),
+    };
+
+    // The keys share the database name, which does not change the distance but should not raise the maximum
+    let missing = missing.to_string();
+    let max_distance = ((missing.len() - database_name.len()) / 4).max(2);
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    for (table_name, table) in tables {
+        let table_key = match TableKey::from_store(database_name, table_name) {
+            Some(table_key) => table_key,
+            None => continue,
+        };
+        for column_name in table.columns.keys() {
+            let candidate = table_key.column(column_name).to_string();
+            let distance = get_distance(&missing, &candidate);
+            if distance <= max_distance {
+                suggestions.push(Suggestion { key: candidate, distance });
+            }
+        }
+    }
+
+    suggestions.sort_by(|a, b| (a.distance, &a.key).cmp(&(b.distance, &b.key)));
+    suggestions.truncate(3);
+    suggestions
+}
+
+/// `suggestions` is `None` when the key still exists
+fn print_suggestions(key: &str, suggestions: &Option<Vec<Suggestion>>) {
+    match suggestions {
+        None => {}
+        Some(suggestions) if suggestions.is_empty() => println!("\t{} no longer exists and no similar column was found", key),
+        Some(suggestions) => {
+            let keys: Vec<&str> = suggestions.iter().map(|suggestion| suggestion.key.as_str()).collect();
+            println!("\t{} no longer exists, did you mean: {}?", key, keys.join(", "));
+        }
+    }
+}
+
+/// The key a stale reference side should be rewritten to: the key itself when it still exists, or
+/// its closest suggestion when no other suggestion is just as close
+fn get_rewritten_key(key: &str, suggestions: &Option<Vec<Suggestion>>) -> Option<String> {
+    match suggestions.as_deref() {
+        None => Some(key.to_string()),
+        Some([suggestion]) => Some(suggestion.key.to_string()),
+        Some([first, second, ..]) if first.distance < second.distance => Some(first.key.to_string()),
+        Some(_) => None,
+    }
+}
+
+fn remove_custom_reference(custom_references: &mut BTreeMap<String, Vec<String>>, key: &str, referenced_key: &str) {
+    if let Some(referenced_keys) = custom_references.get_mut(key) {
+        referenced_keys.retain(|existing_referenced_key| existing_referenced_key != referenced_key);
+        if referenced_keys.is_empty() {
+            custom_references.remove(key);
+        }
+    }
+}
+
+/// Levenshtein distance between two strings
+fn get_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current_row: Vec<usize> = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+            current_row.push((previous_row[j] + substitution_cost)
+                .min(previous_row[j + 1] + 1)
+                .min(current_row[j] + 1));
+        }
+        previous_row = current_row;
+    }
+
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeMap;
+
+    use crate::{config::{Config, ProjectDatabase, ProjectDatabaseConnection, ProjectDatabaseTable}, db::DatabaseType};
+
+    use super::{get_distance, get_rewritten_key, get_suggestions, Suggestion};
+
+    fn config() -> Config {
+        let tables: BTreeMap<String, ProjectDatabaseTable> = serde_yaml::from_str("
+main___orders:
+  columns:
+    id: { data_type: int, is_primary_key: true, is_nullable: false, is_unique: false, is_auto_increment: true, ordinal_position: 1 }
+    user_id: { data_type: int, is_primary_key: false, is_nullable: false, is_unique: false, is_auto_increment: false, ordinal_position: 2 }
+    total: { data_type: int, is_primary_key: false, is_nullable: false, is_unique: false, is_auto_increment: false, ordinal_position: 3 }
+main___users:
+  columns:
+    id: { data_type: int, is_primary_key: true, is_nullable: false, is_unique: false, is_auto_increment: true, ordinal_position: 1 }
+").unwrap();
+
+        let connection = ProjectDatabaseConnection {
+            r#type: DatabaseType::MySql,
+            host: String::from("localhost"),
+            port: 3306,
+            username: String::from("user"),
+            password: String::from("password"),
+        };
+        let mut database = ProjectDatabase::new(connection, None);
+        database.tables = Some(tables);
+
+        let mut databases = BTreeMap::new();
+        databases.insert(String::from("shop"), database);
+        Config::new(String::from("shop"), databases, None, None)
+    }
+
+    fn suggestion(key: &str, distance: usize) -> Suggestion {
+        Suggestion { key: key.to_string(), distance }
+    }
+
+    #[test]
+    fn test_get_distance() {
+        assert_eq!(get_distance("user_id", "user_id"), 0);
+        assert_eq!(get_distance("usr_id", "user_id"), 1);
+        assert_eq!(get_distance("kitten", "sitting"), 3);
+        assert_eq!(get_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_get_suggestions() {
+        let config = config();
+
+        let keys: Vec<(String, usize)> = get_suggestions(&config, "shop___main___orders.usr_id").into_iter()
+            .map(|suggestion| (suggestion.key, suggestion.distance))
+            .collect();
+        assert_eq!(keys, vec![(String::from("shop___main___orders.user_id"), 1), (String::from("shop___main___orders.id"), 4)]);
+
+        assert!(get_suggestions(&config, "shop___main___invoices.amount").is_empty());
+        assert!(get_suggestions(&config, "other___main___orders.user_id").is_empty());
+        assert!(get_suggestions(&config, "not a key").is_empty());
+    }
+
+    #[test]
+    fn test_get_rewritten_key() {
+        let key = "shop___main___orders.usr_id";
+        assert_eq!(get_rewritten_key(key, &None), Some(key.to_string()));
+        assert_eq!(get_rewritten_key(key, &Some(Vec::new())), None);
+        assert_eq!(get_rewritten_key(key, &Some(vec![suggestion("a", 2)])), Some(String::from("a")));
+        assert_eq!(get_rewritten_key(key, &Some(vec![suggestion("a", 1), suggestion("b", 2)])), Some(String::from("a")));
+        assert_eq!(get_rewritten_key(key, &Some(vec![suggestion("a", 1), suggestion("b", 1)])), None);
+    }
+
+}
@@ -1,42 +1,193 @@
 use anyhow::Result;
-use crate::config::{self, Config};
+use serde::Serialize;
+use crate::{config::{self, Config}, error::Error, key::ColumnKey, log, output::{self, Output}, schema, workspace::{self, Workspace}};
 
 use super::Command;
 
 pub struct ValidateCommand {
     pub project: String,
+    pub workspace: bool,
+}
+
+#[derive(Serialize)]
+struct ValidateResult {
+    findings: Vec<Finding>,
+}
+
+/// A problem found in the config files, identified by its `code`
+#[derive(Serialize)]
+struct Finding {
+    code: &'static str,
+    message: String,
+    /// The project whose file has the problem, when validating a workspace
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    /// The line of the file with the problem, when it is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    /// The setting that does not match the format of the project files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// The column key with the problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+}
+
+impl Finding {
+
+    fn new(code: &'static str, key: &str, message: String) -> Self {
+        Finding { code, message, project: None, line: None, path: None, key: Some(key.to_string()) }
+    }
+
+}
+
+impl Output for ValidateResult {
+
+    fn print_text(&self) {
+        for finding in &self.findings {
+            let location = match (finding.line, &finding.path) {
+                (Some(line), Some(path)) => format!("Line {} ({}): ", line, path),
+                (None, Some(path)) => format!("{}: ", path),
+                _ => String::new(),
+            };
+            match &finding.project {
+                Some(project) => println!("{}: {}{}", project, location, finding.message),
+                None => println!("{}{}", location, finding.message),
+            }
+        }
+
+        if self.findings.is_empty() {
+            println!("No problems found");
+        }
+    }
+
 }
 
 impl Command for ValidateCommand {
 
     fn get_starting_message(&self) -> String {
-        format!("Validating the config file of the '{}' project", self.project)
+        match self.workspace {
+            true => format!("Validating the config files of the '{}' workspace", self.project),
+            false => format!("Validating the config file of the '{}' project", self.project),
+        }
     }
 
     async fn execute(&self) -> Result<()> {
+        let findings = match self.workspace {
+            true => self.validate_workspace()?,
+            false => self.validate_project()?,
+        };
+
+        let problems = findings.len();
+        output::print(&ValidateResult { findings })?;
+
+        match problems {
+            0 => Ok(()),
+            problems => Err(Error::Validation(format!("{} problems found", problems)).into()),
+        }
+    }
+
+}
+
+impl ValidateCommand {
+
+    fn validate_project(&self) -> Result<Vec<Finding>> {
+        let findings = validate_schema(&self.project)?;
+        if !findings.is_empty() {
+            return Ok(findings);
+        }
+
         let config: Config = config::load(&self.project)?;
-        validate_keys_in_references_and_custom_references(&config);
-        validate_keys_with_multiple_referenced_keys(&config);
-        Ok(())
+        let mut findings = validate_key_format(&config);
+        findings.extend(validate_keys_in_references_and_custom_references(&config));
+        findings.extend(validate_keys_with_multiple_referenced_keys(&config));
+        Ok(findings)
+    }
+
+    fn validate_workspace(&self) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+        for project in workspace::get_projects(&self.project)? {
+            log::info(format!("Validating the config file of the '{}' project", project));
+            for mut finding in validate_schema(&project)? {
+                finding.project = Some(project.to_string());
+                findings.push(finding);
+            }
+        }
+        if !findings.is_empty() {
+            return Ok(findings);
+        }
+
+        let workspace = workspace::load(&self.project)?;
+        let mut findings: Vec<Finding> = Vec::new();
+        for (project, project_config) in &workspace.projects {
+            for mut finding in validate_key_format(project_config) {
+                finding.project = Some(project.to_string());
+                findings.push(finding);
+            }
+        }
+
+        let config = workspace.to_config();
+        findings.extend(validate_keys_in_references_and_custom_references(&config));
+        findings.extend(validate_keys_with_multiple_referenced_keys(&config));
+        findings.extend(validate_workspace_references(&workspace, &config));
+        Ok(findings)
     }
 
 }
 
-fn validate_keys_in_references_and_custom_references(config: &Config) {    
+/// Finds every part of the config file that does not match the JSON Schema of the project files
+/// (if any, the file cannot be loaded for the other validations).
+fn validate_schema(project: &String) -> Result<Vec<Finding>> {
+    let (source, value) = config::load_value(project)?;
+    let violations = schema::validate(&source, &value)?;
+
+    Ok(violations.into_iter()
+        .map(|violation| Finding {
+            code: "schema_violation",
+            message: violation.message,
+            project: None,
+            line: violation.line,
+            path: Some(match violation.path.as_str() {
+                "" => String::from("root"),
+                path => path.to_string(),
+            }),
+            key: None,
+        })
+        .collect())
+}
+
+/// Checks that the keys of the custom references follow the `{database_name}___{schema_name}___{table_name}.{column_name}` format
+fn validate_key_format(config: &Config) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    for (key, referenced_keys) in config.custom_references.iter().flatten() {
+        for key in std::iter::once(key).chain(referenced_keys) {
+            if ColumnKey::parse(key).is_err() {
+                findings.push(Finding::new("invalid_key", key,
+                    format!("Key '{}' in 'custom_references' is not a valid column key ({{database_name}}___{{schema_name}}___{{table_name}}.{{column_name}})", key)));
+            }
+        }
+    }
+    findings
+}
+
+fn validate_keys_in_references_and_custom_references(config: &Config) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
     if let (Some(references), Some(custom_references)) = (&config.references, &config.custom_references) {
         for key in references.keys() {
             if custom_references.contains_key(key) {
-                println!("Key '{}' exists in both 'references' and 'custom_references'", key);
+                findings.push(Finding::new("duplicate_reference", key, format!("Key '{}' exists in both 'references' and 'custom_references'", key)));
             }
         }
     }
+    findings
 }
 
-fn validate_keys_with_multiple_referenced_keys(config: &Config) {
+fn validate_keys_with_multiple_referenced_keys(config: &Config) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
     if let Some(references) = &config.references {
         for (key, referenced_keys) in references {
             if referenced_keys.len() > 1 {
-                println!("Key '{}' in 'references' has more than one referenced key", key);
+                findings.push(Finding::new("multiple_referenced_keys", key, format!("Key '{}' in 'references' has more than one referenced key", key)));
             }
         }
     }
@@ -44,8 +195,31 @@ fn validate_keys_with_multiple_referenced_keys(config: &Config) {
     if let Some(custom_references) = &config.custom_references {
         for (key, referenced_keys) in custom_references {
             if referenced_keys.len() > 1 {
-                println!("Key '{}' in 'custom_references' has more than one referenced key", key);
+                findings.push(Finding::new("multiple_referenced_keys", key, format!("Key '{}' in 'custom_references' has more than one referenced key", key)));
             }
         }
     }
-}
\ No newline at end of file
+    findings
+}
+
+/// Checks that the references between projects of a workspace point to scanned columns of its projects
+fn validate_workspace_references(workspace: &Workspace, config: &Config) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    for (key, referenced_keys) in workspace.custom_references.iter().flatten() {
+        for key in std::iter::once(key).chain(referenced_keys) {
+            let finding = match workspace::split(key) {
+                Some((project, _)) if !workspace.projects.contains_key(&project) => Finding::new("unknown_project", key,
+                    format!("Key '{}' in the workspace's 'custom_references' belongs to '{}', which is not a project of the workspace", key, project)),
+                Some(_) if config.get_column(key).is_none() => Finding::new("unknown_column", key,
+                    format!("Key '{}' in the workspace's 'custom_references' does not match a scanned column", key)),
+                Some(_) => continue,
+                None if ColumnKey::parse(key).is_err() => Finding::new("invalid_key", key,
+                    format!("Key '{}' in the workspace's 'custom_references' is not a valid column key ({{project}}::{{database_name}}___{{schema_name}}___{{table_name}}.{{column_name}})", key)),
+                None => Finding::new("missing_project", key,
+                    format!("Key '{}' in the workspace's 'custom_references' does not start with its project ({{project}}::{{key}})", key)),
+            };
+            findings.push(finding);
+        }
+    }
+    findings
+}
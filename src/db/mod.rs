@@ -1,24 +1,38 @@
+use std::str::FromStr;
+
 use anyhow::Result;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use crate::config::{DatabaseConfiguration, ProjectConfiguration, ProjectDatabaseConnection};
 use mysql::MysqlDatabase;
 
 mod mysql;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum DatabaseType {
     MySql,
 }
 
 impl DatabaseType {
-    fn as_string(&self) -> &'static str {
+    pub fn as_string(&self) -> &'static str {
         match self {
             DatabaseType::MySql => "mysql",
         }
     }
 }
 
+impl FromStr for DatabaseType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "mysql" => Ok(DatabaseType::MySql),
+            _ => Err(format!("'{}' is not a supported database type (supported: mysql)", value)),
+        }
+    }
+}
+
 #[derive(Debug, sqlx::FromRow)]
 pub struct ColumnInfo {
     pub schema_name: String,
@@ -44,18 +58,30 @@ pub struct ReferenceInfo {
     pub referenced_column_name: String
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct SchemaFingerprint {
+    pub schema_name: String,
+    pub fingerprint: String
+}
+
 pub trait DatabaseEngine {
-    async fn scan_tables_and_columns(&self) -> Result<Vec<ColumnInfo>>;
+    async fn scan_tables_and_columns<F: FnMut(ColumnInfo)>(&self, on_column: F) -> Result<()>;
     async fn scan_references(&self) -> Result<Vec<ReferenceInfo>>;
+    async fn scan_fingerprints(&self) -> Result<Vec<SchemaFingerprint>>;
+    async fn list_schemas(&self) -> Result<Vec<String>>;
 }
 
-pub async fn scan_tables_and_columns(
+/// Streams the columns of every scanned table into `on_column`, one row at a time and in no
+/// particular order, so the whole catalog never has to be held in memory at once.
+pub async fn scan_tables_and_columns<F: FnMut(ColumnInfo)>(
     connection_info: ProjectDatabaseConnection,
     configurations: Option<ProjectConfiguration>,
     database_configurations: Option<DatabaseConfiguration>,
-) -> Result<Vec<ColumnInfo>> {
+    schemas: Option<Vec<String>>,
+    on_column: F,
+) -> Result<()> {
     match connection_info.r#type {
-        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations, database_configurations }.scan_tables_and_columns().await
+        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations, database_configurations, schemas }.scan_tables_and_columns(on_column).await
     }
 }
 
@@ -63,8 +89,26 @@ pub async fn scan_references(
     connection_info: ProjectDatabaseConnection,
     configurations: Option<ProjectConfiguration>,
     database_configurations: Option<DatabaseConfiguration>,
+    schemas: Option<Vec<String>>,
 ) -> Result<Vec<ReferenceInfo>> {
     match connection_info.r#type {
-        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations, database_configurations }.scan_references().await
+        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations, database_configurations, schemas }.scan_references().await
+    }
+}
+
+pub async fn scan_fingerprints(
+    connection_info: ProjectDatabaseConnection,
+    configurations: Option<ProjectConfiguration>,
+    database_configurations: Option<DatabaseConfiguration>,
+) -> Result<Vec<SchemaFingerprint>> {
+    match connection_info.r#type {
+        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations, database_configurations, schemas: None }.scan_fingerprints().await
     }
-}
\ No newline at end of file
+}
+
+/// Lists every schema of the server (ignored or not), which also tests the connection
+pub async fn list_schemas(connection_info: ProjectDatabaseConnection) -> Result<Vec<String>> {
+    match connection_info.r#type {
+        DatabaseType::MySql => MysqlDatabase{ connection_info, configurations: None, database_configurations: None, schemas: None }.list_schemas().await
+    }
+}
@@ -1,39 +1,59 @@
 use anyhow::{anyhow, Result};
+use futures_util::TryStreamExt;
 use sqlx::{MySqlConnection, Connection};
-use crate::config::{DatabaseConfiguration, ProjectConfiguration, ProjectDatabaseConnection};
+use crate::{config::{DatabaseConfiguration, ProjectConfiguration, ProjectDatabaseConnection}, error::Error};
 
-use super::{DatabaseEngine, ColumnInfo, ReferenceInfo};
+use super::{DatabaseEngine, ColumnInfo, ReferenceInfo, SchemaFingerprint};
 
 pub struct MysqlDatabase {
     pub connection_info: ProjectDatabaseConnection,
     pub configurations: Option<ProjectConfiguration>,
     pub database_configurations: Option<DatabaseConfiguration>,
+    pub schemas: Option<Vec<String>>,
 }
 
-impl DatabaseEngine for MysqlDatabase {
-
-    async fn scan_tables_and_columns(&self) -> Result<Vec<ColumnInfo>> {
+impl MysqlDatabase {
 
+    /// The conditions on the schema column of a query, with the schema names to bind to their placeholders in that order
+    fn get_where_clause(&self, schema_column: &str) -> (String, Vec<String>) {
         let mut where_clauses: Vec<String> = Vec::new();
-        let default_where_clause = String::from("1 = 1");
+        let mut values: Vec<String> = Vec::new();
         if let Some(configs) = &self.configurations {
             if let Some(schemas_by_type) = &configs.schemas_to_ignore {
-                if schemas_by_type.contains_key(self.connection_info.r#type.as_string()) {
-                    where_clauses.push(format!("table_schema NOT IN ('{}')", schemas_by_type.get(self.connection_info.r#type.as_string()).unwrap().join("\', \'")));
+                if let Some(schemas) = schemas_by_type.get(self.connection_info.r#type.as_string()) {
+                    where_clauses.push(get_schema_filter(schema_column, schemas, true, &mut values));
                 }
             }
         }
 
         if let Some(configs) = &self.database_configurations {
-            if let Some(schemas_by_type) = &configs.schemas_to_ignore {
-                where_clauses.push(format!("table_schema NOT IN ('{}')", schemas_by_type.join("\', \'")));
+            if let Some(schemas) = &configs.schemas_to_ignore {
+                where_clauses.push(get_schema_filter(schema_column, schemas, true, &mut values));
             }
         }
 
-        if where_clauses.len() == 0 {
-            where_clauses.push(default_where_clause);
+        if let Some(schemas) = &self.schemas {
+            where_clauses.push(get_schema_filter(schema_column, schemas, false, &mut values));
         }
-        
+
+        if where_clauses.is_empty() {
+            where_clauses.push(String::from("1 = 1"));
+        }
+
+        (where_clauses.join(" AND "), values)
+    }
+
+    async fn connect(&self) -> Result<MySqlConnection> {
+        MySqlConnection::connect(&self.connection_info.get_connection_string()).await
+            .map_err(|err| anyhow!(Error::Connection(format!("Could not connect to '{}': {}", &self.connection_info, self.connection_info.redact(&err.to_string())))))
+    }
+
+}
+
+impl DatabaseEngine for MysqlDatabase {
+
+    async fn scan_tables_and_columns<F: FnMut(ColumnInfo)>(&self, mut on_column: F) -> Result<()> {
+        let (where_clause, schemas) = self.get_where_clause("table_schema");
         let query: String = format!("
         SELECT 
             table_schema schema_name,
@@ -48,19 +68,32 @@ impl DatabaseEngine for MysqlDatabase {
             column_default AS default_value,
             ordinal_position
         FROM information_schema.columns
-        WHERE {}
-        ORDER BY table_name, ordinal_position;
-        ", where_clauses.join(" AND "));
-
-        let mut conn = MySqlConnection::connect(&self.connection_info.get_connection_string()).await
-            .map_err(|err| anyhow!(format!("Could not connect to '{}': {}", &self.connection_info.get_connection_string(), err)))?;
-        
-        sqlx::query_as::<_, ColumnInfo>(&query).fetch_all(&mut conn).await
-            .map_err(|err| anyhow!(format!("Could not run the scan query on '{}': {}", &self.connection_info.get_connection_string(), err)))
+        WHERE {};
+        ", where_clause);
+
+        let mut query = sqlx::query_as::<_, ColumnInfo>(&query);
+        for schema in schemas {
+            query = query.bind(schema);
+        }
+
+        let mut conn = self.connect().await?;
+        let mut rows = query.fetch(&mut conn);
+        while let Some(column_info) = rows.try_next().await
+            .map_err(|err| anyhow!(Error::Query(format!("Could not run the scan query on '{}': {}", &self.connection_info, self.connection_info.redact(&err.to_string())))))? {
+            on_column(column_info);
+        }
+
+        Ok(())
     }
 
     async fn scan_references(&self) -> Result<Vec<ReferenceInfo>> {
-        let query: String = String::from("
+        let mut schemas: Vec<String> = Vec::new();
+        let where_clause = match &self.schemas {
+            Some(schemas_to_scan) => get_schema_filter("table_schema", schemas_to_scan, false, &mut schemas),
+            None => String::from("1 = 1"),
+        };
+
+        let query: String = format!("
         SELECT 
             table_schema schema_name,
             table_name,
@@ -70,14 +103,117 @@ impl DatabaseEngine for MysqlDatabase {
             referenced_column_name
         FROM information_schema.key_column_usage
         WHERE
-            referenced_column_name IS NOT NULL;
-        ");
-
-        let mut conn = MySqlConnection::connect(&self.connection_info.get_connection_string()).await
-            .map_err(|err| anyhow!(format!("Could not connect to '{}': {}", &self.connection_info.get_connection_string(), err)))?;
-        
-        sqlx::query_as::<_, ReferenceInfo>(query.as_str()).fetch_all(&mut conn).await
-            .map_err(|err| anyhow!(format!("Could not run the scan query on '{}': {}", &self.connection_info.get_connection_string(), err)))
+            referenced_column_name IS NOT NULL
+            AND {};
+        ", where_clause);
+
+        let mut query = sqlx::query_as::<_, ReferenceInfo>(&query);
+        for schema in schemas {
+            query = query.bind(schema);
+        }
+
+        let mut conn = self.connect().await?;
+        query.fetch_all(&mut conn).await
+            .map_err(|err| anyhow!(Error::Query(format!("Could not run the scan query on '{}': {}", &self.connection_info, self.connection_info.redact(&err.to_string())))))
+    }
+
+    // The fingerprint of a schema changes whenever one of its tables is created or rebuilt, or
+    // when any column or foreign key is added, removed or altered. Tables' `update_time` is left
+    // out on purpose: InnoDB bumps it on every data change, which would defeat the incremental scan.
+    async fn scan_fingerprints(&self) -> Result<Vec<SchemaFingerprint>> {
+        let (where_clause, schemas) = self.get_where_clause("c.table_schema");
+        let query: String = format!("
+        SELECT
+            c.table_schema schema_name,
+            CAST(CONCAT_WS(':',
+                COUNT(*),
+                SUM(CRC32(CONCAT_WS('|',
+                    c.table_name,
+                    c.column_name,
+                    c.column_type,
+                    c.is_nullable,
+                    c.column_key,
+                    c.extra,
+                    COALESCE(c.column_default, 'NULL'),
+                    c.ordinal_position
+                ))),
+                COALESCE(MAX(t.create_time), ''),
+                COALESCE(MAX(r.reference_checksum), 0)
+            ) AS CHAR) AS fingerprint
+        FROM information_schema.columns c
+        JOIN information_schema.tables t
+            ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+        LEFT JOIN (
+            SELECT
+                table_schema,
+                SUM(CRC32(CONCAT_WS('|',
+                    table_name,
+                    column_name,
+                    referenced_table_schema,
+                    referenced_table_name,
+                    referenced_column_name
+                ))) AS reference_checksum
+            FROM information_schema.key_column_usage
+            WHERE referenced_column_name IS NOT NULL
+            GROUP BY table_schema
+        ) r ON r.table_schema = c.table_schema
+        WHERE {}
+        GROUP BY c.table_schema;
+        ", where_clause);
+
+        let mut query = sqlx::query_as::<_, SchemaFingerprint>(&query);
+        for schema in schemas {
+            query = query.bind(schema);
+        }
+
+        let mut conn = self.connect().await?;
+        query.fetch_all(&mut conn).await
+            .map_err(|err| anyhow!(Error::Query(format!("Could not run the fingerprint query on '{}': {}", &self.connection_info, self.connection_info.redact(&err.to_string())))))
+    }
+
+    async fn list_schemas(&self) -> Result<Vec<String>> {
+        let query = "SELECT CAST(schema_name AS CHAR) FROM information_schema.schemata ORDER BY schema_name";
+
+        let mut conn = self.connect().await?;
+        sqlx::query_scalar::<_, String>(query).fetch_all(&mut conn).await
+            .map_err(|err| anyhow!(Error::Query(format!("Could not list the schemas of '{}': {}", &self.connection_info, self.connection_info.redact(&err.to_string())))))
+    }
+
+}
+
+/// `{schema_column} IN (?, ?, …)` (or `NOT IN` when `excluded`), adding the schema names to the values
+/// to bind: they are never written into the query, so any schema name is matched as it is
+fn get_schema_filter(schema_column: &str, schemas: &[String], excluded: bool, values: &mut Vec<String>) -> String {
+    match (schemas.is_empty(), excluded) {
+        (true, true) => String::from("1 = 1"),
+        (true, false) => String::from("1 = 0"),
+        (false, _) => {
+            values.extend(schemas.iter().cloned());
+            let operator = match excluded {
+                true => "NOT IN",
+                false => "IN",
+            };
+            format!("{} {} ({})", schema_column, operator, vec!["?"; schemas.len()].join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::get_schema_filter;
+
+    #[test]
+    fn test_schema_filter_binds_the_schema_names() {
+        let mut values: Vec<String> = Vec::new();
+        let schemas = vec![String::from("main"), String::from("o'brien'); DROP TABLE users; --")];
+
+        assert_eq!(get_schema_filter("table_schema", &schemas, false, &mut values), "table_schema IN (?, ?)");
+        assert_eq!(get_schema_filter("table_schema", &[String::from("sys")], true, &mut values), "table_schema NOT IN (?)");
+        assert_eq!(values, vec!["main", "o'brien'); DROP TABLE users; --", "sys"]);
+
+        assert_eq!(get_schema_filter("table_schema", &[], false, &mut values), "1 = 0");
+        assert_eq!(values.len(), 3);
     }
 
-}
\ No newline at end of file
+}
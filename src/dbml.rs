@@ -1,22 +1,28 @@
-use std::{fs::{OpenOptions, File}, io::Write};
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
 
+use crate::{config, key::ColumnKey};
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct DBML {
     project: String,
-    file: File
+    path: PathBuf,
+    contents: String,
 }
 
 impl DBML {
 
-    pub fn write(&mut self, content: String) -> Result<()> {
-        self.file.write_all(content.as_bytes())
-            .map_err(|err| anyhow!(format!("Could not write content to the DBML file for project {}: {}", self.project, err)))?;
-        Ok(())
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, content: String) {
+        self.contents.push_str(&content);
     }
 
     pub fn save(&mut self) -> Result<()> {
-        self.file.flush()
+        config::write_file(&self.path, &self.contents)
             .map_err(|err| anyhow!(format!("Could not save the DBML file for project {}: {}", self.project, err)))?;
         Ok(())
     }
@@ -24,22 +30,30 @@ impl DBML {
 }
 
 pub fn init(project: &String) -> Result<DBML> {
-    let file_path = get_file_path(project)?;
-
-    let file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(file_path)
-        .map_err(|err| anyhow!(format!("Could not open/create the DBML file for project {}: {}", project, err)))?;
-
-    Ok(DBML { 
+    Ok(DBML {
         project: project.to_string(),
-        file
+        path: get_file_path(project)?,
+        contents: String::new(),
     })
 }
 
-fn get_file_path(project: &String) -> Result<String> {
-    let home = home::home_dir().ok_or(anyhow!("Could not detect the current user's home directory."))?;
-    Ok(format!("{}/.dbml/{}.dbml", home.to_str().unwrap(), project))
-}
\ No newline at end of file
+fn get_file_path(project: &String) -> Result<PathBuf> {
+    Ok(config::get_config_dir()?.join(format!("{}.dbml", project)))
+}
+
+/// A table or column name as written in DBML: names that are not made only of letters, digits and
+/// underscores (such as the `{project}::{database_name}` of workspaces) are quoted
+pub fn format_name(name: &str) -> String {
+    match name.chars().all(|character| character.is_alphanumeric() || character == '_') {
+        true => name.to_string(),
+        false => format!("\"{}\"", name.replace('"', "\\\"")),
+    }
+}
+
+/// A column key as written in DBML references (`{table_name}.{column_name}`)
+pub fn format_key(key: &str) -> String {
+    match ColumnKey::parse(key) {
+        Ok(key) => format!("{}.{}", format_name(&key.table.get_dbml_name()), format_name(&key.column_name)),
+        Err(_) => format_name(key),
+    }
+}
@@ -0,0 +1,52 @@
+use thiserror::Error as ThisError;
+
+/// The failures that scripts may want to tell apart, each with its own exit code. Any other error
+/// exits with 1, and invalid command line arguments with 2.
+#[derive(Debug, ThisError)]
+pub enum Error {
+    /// A project or workspace file (or a file it includes) does not exist. Exit code 3.
+    #[error("{0}")]
+    ConfigNotFound(String),
+    /// A project, workspace or scan store file is not valid YAML or does not match its format. Exit code 4.
+    #[error("{0}")]
+    Parse(String),
+    /// A database server could not be reached or refused the credentials. Exit code 5.
+    #[error("{0}")]
+    Connection(String),
+    /// A query failed on a database server. Exit code 6.
+    #[error("{0}")]
+    Query(String),
+    /// A key, table, database or setting given to a command does not exist or is malformed. Exit code 7.
+    #[error("{0}")]
+    InvalidKey(String),
+    /// `validate` found problems in the config. Exit code 8.
+    #[error("{0}")]
+    Validation(String),
+    /// The history of a project has no change to undo, or none with the id given to `undo --to`. Exit code 9.
+    #[error("{0}")]
+    HistoryNotFound(String),
+}
+
+impl Error {
+
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            Error::ConfigNotFound(_) => 3,
+            Error::Parse(_) => 4,
+            Error::Connection(_) => 5,
+            Error::Query(_) => 6,
+            Error::InvalidKey(_) => 7,
+            Error::Validation(_) => 8,
+            Error::HistoryNotFound(_) => 9,
+        }
+    }
+
+}
+
+/// The exit code of a failed command: the one of its `Error`, or 1 for any other error
+pub fn get_exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<Error>() {
+        Some(err) => err.get_exit_code(),
+        None => 1,
+    }
+}
@@ -0,0 +1,179 @@
+use std::{fs, path::{Path, PathBuf}, sync::OnceLock, time::{SystemTime, UNIX_EPOCH}};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+/// How many previous states are kept per project, the oldest ones are removed first
+const HISTORY_SIZE: usize = 20;
+
+/// The project file in a history entry (the scan stores are in `stores/`)
+const PROJECT_FILE_NAME: &str = "project.yaml";
+const ENTRY_FILE_NAME: &str = "entry.yaml";
+
+/// A previous state of a project: its project file and scan stores as they were before a command
+/// changed them. Entries are kept in `{project}/.history/{id}/`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the entry was recorded, in milliseconds since the Unix epoch (the oldest entries have the smallest ids)
+    pub id: u64,
+    /// What the command that made the change was doing, such as "Scanning project 'shop'"
+    pub description: String,
+}
+
+static DESCRIPTION: OnceLock<String> = OnceLock::new();
+
+/// Sets what the running command is doing, which describes the entries it records
+pub fn set_description(description: String) {
+    let _ = DESCRIPTION.set(description);
+}
+
+/// Copies the project file and scan stores of a project to a new history entry, before they are
+/// changed, removing the oldest entries beyond `HISTORY_SIZE`
+pub fn record(project: &String) -> Result<()> {
+    let mut id = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or_default();
+    while get_entry_dir(project, id)?.exists() {
+        id += 1;
+    }
+
+    let entry = HistoryEntry {
+        id,
+        description: DESCRIPTION.get().cloned().unwrap_or_default(),
+    };
+
+    let entry_dir = get_entry_dir(project, id)?;
+    let stores_dir = entry_dir.join("stores");
+    create_dir(&stores_dir)?;
+
+    let path = config::get_project_path(project)?;
+    if path.exists() {
+        copy(&path, &entry_dir.join(PROJECT_FILE_NAME))?;
+    }
+    for (database_name, store_path) in get_stores(&config::get_store_dir(project)?)? {
+        copy(&store_path, &stores_dir.join(format!("{}.yaml", database_name)))?;
+    }
+
+    let contents = serde_yaml::to_string(&entry)
+        .map_err(|err| anyhow!(format!("Could not generate the history entry of project '{}': {}", project, err)))?;
+    fs::write(entry_dir.join(ENTRY_FILE_NAME), contents)
+        .map_err(|err| anyhow!(format!("Could not write the history entry of project '{}': {}", project, err)))?;
+
+    let entries = list(project)?;
+    for entry in entries.iter().skip(HISTORY_SIZE) {
+        remove(project, entry)?;
+    }
+
+    Ok(())
+}
+
+/// The history entries of a project, the most recent first
+pub fn list(project: &String) -> Result<Vec<HistoryEntry>> {
+    let history_dir = get_history_dir(project)?;
+    let directories = match fs::read_dir(&history_dir) {
+        Ok(directories) => directories,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    for directory in directories.flatten() {
+        let path = directory.path().join(ENTRY_FILE_NAME);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            // An entry whose recording was interrupted
+            Err(_) => continue,
+        };
+        let entry = serde_yaml::from_str::<HistoryEntry>(&contents)
+            .map_err(|err| anyhow!(format!("Could not parse the history entry at '{}': {}", path.display(), err)))?;
+        entries.push(entry);
+    }
+
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.id));
+    Ok(entries)
+}
+
+/// Puts back the project file and scan stores of a history entry, removing the scan stores that did not exist then
+pub fn restore(project: &String, entry: &HistoryEntry) -> Result<()> {
+    let entry_dir = get_entry_dir(project, entry.id)?;
+    let path = config::get_project_path(project)?;
+    let store_dir = config::get_store_dir(project)?;
+
+    let project_file = entry_dir.join(PROJECT_FILE_NAME);
+    if project_file.exists() {
+        restore_file(&project_file, &path)?;
+    }
+
+    let stores = get_stores(&entry_dir.join("stores"))?;
+    for (database_name, store_path) in get_stores(&store_dir)? {
+        if !stores.iter().any(|(restored_database_name, _)| *restored_database_name == database_name) {
+            config::remove_file(&store_path)
+                .map_err(|err| anyhow!(format!("Could not remove '{}': {}", store_path.display(), err)))?;
+        }
+    }
+    if !stores.is_empty() && !config::is_dry_run() {
+        create_dir(&store_dir)?;
+    }
+    for (database_name, entry_store_path) in stores {
+        restore_file(&entry_store_path, &store_dir.join(format!("{}.yaml", database_name)))?;
+    }
+
+    Ok(())
+}
+
+/// Removes a history entry (unless with `--dry-run`)
+pub fn remove(project: &String, entry: &HistoryEntry) -> Result<()> {
+    if config::is_dry_run() {
+        return Ok(());
+    }
+
+    let entry_dir = get_entry_dir(project, entry.id)?;
+    fs::remove_dir_all(&entry_dir)
+        .map_err(|err| anyhow!(format!("Could not remove the history entry at '{}': {}", entry_dir.display(), err)))
+}
+
+fn get_history_dir(project: &String) -> Result<PathBuf> {
+    Ok(config::get_store_dir(project)?.join(".history"))
+}
+
+fn get_entry_dir(project: &String, id: u64) -> Result<PathBuf> {
+    Ok(get_history_dir(project)?.join(id.to_string()))
+}
+
+/// The scan stores (`{database_name}.yaml`) of a directory, by database name
+fn get_stores(directory: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut stores: Vec<(String, PathBuf)> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "yaml") {
+            if let Some(database_name) = path.file_stem() {
+                stores.push((database_name.to_string_lossy().to_string(), path));
+            }
+        }
+    }
+
+    stores.sort();
+    Ok(stores)
+}
+
+fn restore_file(path: &Path, target: &Path) -> Result<()> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow!(format!("Could not read '{}': {}", path.display(), err)))?;
+    config::write_file(target, &contents)
+        .map_err(|err| anyhow!(format!("Could not restore '{}': {}", target.display(), err)))
+}
+
+fn create_dir(directory: &Path) -> Result<()> {
+    fs::create_dir_all(directory)
+        .map_err(|err| anyhow!(format!("Could not create the directory '{}': {}", directory.display(), err)))
+}
+
+fn copy(path: &Path, target: &Path) -> Result<()> {
+    fs::copy(path, target)
+        .map_err(|err| anyhow!(format!("Could not copy '{}' to '{}': {}", path.display(), target.display(), err)))?;
+    Ok(())
+}
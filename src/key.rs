@@ -0,0 +1,300 @@
+use std::{collections::BTreeMap, fmt};
+
+use anyhow::Result;
+
+use crate::error::Error;
+
+/// Separates the database, schema and table names of a key
+const NAME_SEPARATOR: &str = "___";
+/// Separates the column name from the table key
+const COLUMN_SEPARATOR: &str = ".";
+
+/// A scanned table: `{database_name}___{schema_name}___{table_name}`. In workspaces, the database
+/// name is prefixed by its project (`{project}::{database_name}`).
+///
+/// Names that contain `.`, `___` or `"`, or that end with `_`, are written between double
+/// quotes (with their `"` doubled), such as `shop___main___"order.items"`. Keys written before names
+/// could be quoted are read the same way as before: the database and schema names end at the first
+/// `___`, and the table name at the first `.`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TableKey {
+    pub database_name: String,
+    pub schema_name: String,
+    pub table_name: String,
+}
+
+/// A scanned column: `{database_name}___{schema_name}___{table_name}.{column_name}` (see `TableKey`)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ColumnKey {
+    pub table: TableKey,
+    pub column_name: String,
+}
+
+impl TableKey {
+
+    pub fn new(database_name: &str, schema_name: &str, table_name: &str) -> Self {
+        TableKey {
+            database_name: database_name.to_string(),
+            schema_name: schema_name.to_string(),
+            table_name: table_name.to_string(),
+        }
+    }
+
+    pub fn parse(key: &str) -> Result<Self> {
+        match split(key, &[NAME_SEPARATOR, NAME_SEPARATOR]).as_deref() {
+            Some([database_name, schema_name, table_name]) => Ok(TableKey::new(database_name, schema_name, table_name)),
+            _ => Err(Error::InvalidKey(format!("'{}' is not a valid table key ({{database_name}}___{{schema_name}}___{{table_name}}, with names containing '.', '___' or '\"' between double quotes)", key)).into()),
+        }
+    }
+
+    /// The key of a table of a database's scan store, whose tables are named `{schema_name}___{table_name}`
+    pub fn from_store(database_name: &str, name: &str) -> Option<Self> {
+        let (schema_name, table_name) = parse_store_name(name)?;
+        Some(TableKey::new(database_name, &schema_name, &table_name))
+    }
+
+    /// The name of the table in its database's scan store: `{schema_name}___{table_name}`
+    pub fn get_store_name(&self) -> String {
+        format_store_name(&self.schema_name, &self.table_name)
+    }
+
+    /// The name of the table in DBML files, which quote names themselves but cannot escape `"` in them.
+    /// The names that would be quoted in keys (or that contain `%`) have their `_`, `"` and `%`
+    /// percent-encoded instead, so that two tables never get the same name.
+    pub fn get_dbml_name(&self) -> String {
+        [&self.database_name, &self.schema_name, &self.table_name]
+            .map(|name| encode_dbml_name(name))
+            .join(NAME_SEPARATOR)
+    }
+
+    pub fn column(&self, column_name: &str) -> ColumnKey {
+        ColumnKey { table: self.clone(), column_name: column_name.to_string() }
+    }
+
+}
+
+impl fmt::Display for TableKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}{}{}", quote(&self.database_name), NAME_SEPARATOR, self.get_store_name())
+    }
+}
+
+impl ColumnKey {
+
+    pub fn parse(key: &str) -> Result<Self> {
+        match split(key, &[NAME_SEPARATOR, NAME_SEPARATOR, COLUMN_SEPARATOR]).as_deref() {
+            Some([database_name, schema_name, table_name, column_name]) => Ok(TableKey::new(database_name, schema_name, table_name).column(column_name)),
+            _ => Err(Error::InvalidKey(format!("'{}' is not a valid column key ({{database_name}}___{{schema_name}}___{{table_name}}.{{column_name}}, with names containing '.', '___' or '\"' between double quotes)", key)).into()),
+        }
+    }
+
+}
+
+impl fmt::Display for ColumnKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}{}{}", self.table, COLUMN_SEPARATOR, quote(&self.column_name))
+    }
+}
+
+/// The schema and table names of a table of a scan store (`{schema_name}___{table_name}`)
+pub fn parse_store_name(name: &str) -> Option<(String, String)> {
+    match split(name, &[NAME_SEPARATOR])?.as_slice() {
+        [schema_name, table_name] => Some((schema_name.to_string(), table_name.to_string())),
+        _ => None,
+    }
+}
+
+pub fn format_store_name(schema_name: &str, table_name: &str) -> String {
+    format!("{}{}{}", quote(schema_name), NAME_SEPARATOR, quote(table_name))
+}
+
+/// The key of a table of a database's scan store (kept as it is after the database name when it is not
+/// named `{schema_name}___{table_name}`)
+pub fn format_table_key(database_name: &str, name: &str) -> String {
+    match TableKey::from_store(database_name, name) {
+        Some(key) => key.to_string(),
+        None => format!("{}{}{}", quote(database_name), NAME_SEPARATOR, name),
+    }
+}
+
+/// Rewrites the column keys of references in their current format (the ones that are not valid keys
+/// are kept as they are, for `validate` to report them)
+pub fn normalize_references(references: BTreeMap<String, Vec<String>>) -> BTreeMap<String, Vec<String>> {
+    let mut normalized: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, referenced_keys) in references {
+        normalized.entry(normalize(key))
+            .or_default()
+            .extend(referenced_keys.into_iter().map(normalize));
+    }
+    normalized
+}
+
+fn normalize(key: String) -> String {
+    match ColumnKey::parse(&key) {
+        Ok(column_key) => column_key.to_string(),
+        Err(_) => key,
+    }
+}
+
+/// A name as written in keys, between double quotes when it could not be told apart from the separators otherwise
+fn quote(name: &str) -> String {
+    match needs_quotes(name) {
+        true => format!("\"{}\"", name.replace('"', "\"\"")),
+        false => name.to_string(),
+    }
+}
+
+fn needs_quotes(name: &str) -> bool {
+    name.ends_with('_')
+        || name.contains(COLUMN_SEPARATOR)
+        || name.contains(NAME_SEPARATOR)
+        || name.contains('"')
+}
+
+/// A name as written in DBML table names. Encoded names have no `_` left and other names neither
+/// contain `___` nor end with `_`, so the separators are always found again.
+fn encode_dbml_name(name: &str) -> String {
+    match needs_quotes(name) || name.contains('%') {
+        true => name.replace('%', "%25").replace('_', "%5F").replace('"', "%22"),
+        false => name.to_string(),
+    }
+}
+
+/// Splits a key into its names, which are separated by the given separators in that order. An
+/// unquoted part of a name ends at the first occurrence of its separator.
+fn split(key: &str, separators: &[&str]) -> Option<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = key;
+
+    for index in 0..=separators.len() {
+        let separator = separators.get(index);
+        let mut name = String::new();
+        loop {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let (unquoted, remaining) = unquote(quoted)?;
+                name.push_str(&unquoted);
+                rest = remaining;
+                continue;
+            }
+
+            let quote_start = rest.find('"');
+            let separator_start = separator.and_then(|separator| rest.find(separator))
+                .filter(|start| quote_start.is_none_or(|quote_start| *start < quote_start));
+            match (separator_start, quote_start) {
+                (Some(start), _) => {
+                    name.push_str(&rest[..start]);
+                    rest = &rest[start + separator.map_or(0, |separator| separator.len())..];
+                    break;
+                }
+                (_, Some(quote_start)) => {
+                    name.push_str(&rest[..quote_start]);
+                    rest = &rest[quote_start..];
+                }
+                (None, None) if separator.is_none() => {
+                    name.push_str(rest);
+                    rest = "";
+                    break;
+                }
+                (None, None) => return None,
+            }
+        }
+
+        if name.is_empty() {
+            return None;
+        }
+        names.push(name);
+    }
+
+    Some(names)
+}
+
+/// Reads a quoted name up to its closing quote (`""` being a quote within the name), returning it
+/// with what follows the closing quote
+fn unquote(quoted: &str) -> Option<(String, &str)> {
+    let mut name = String::new();
+    let mut characters = quoted.char_indices().peekable();
+    while let Some((index, character)) = characters.next() {
+        if character != '"' {
+            name.push(character);
+            continue;
+        }
+
+        match characters.peek() {
+            Some((_, '"')) => {
+                name.push('"');
+                characters.next();
+            }
+            _ => return Some((name, &quoted[index + 1..])),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeSet;
+
+    use crate::dbml;
+
+    use super::{ColumnKey, TableKey};
+
+    #[test]
+    fn test_parse_and_format() {
+        let key = ColumnKey::parse("shop___main___orders.user_id").unwrap();
+        assert_eq!(key, TableKey::new("shop", "main", "orders").column("user_id"));
+        assert_eq!(key.to_string(), "shop___main___orders.user_id");
+
+        let key = TableKey::new("shop", "my_", "order.items").column("say \"hi\"");
+        assert_eq!(key.to_string(), "shop___\"my_\"___\"order.items\".\"say \"\"hi\"\"\"");
+        assert_eq!(ColumnKey::parse(&key.to_string()).unwrap(), key);
+
+        let key = TableKey::new("orders::main", "a___b", "c");
+        assert_eq!(key.to_string(), "orders::main___\"a___b\"___c");
+        assert_eq!(TableKey::parse(&key.to_string()).unwrap(), key);
+    }
+
+    #[test]
+    fn test_parse_keys_written_before_quoting() {
+        let key = ColumnKey::parse("shop___my.schema___order___items.data.id").unwrap();
+        assert_eq!(key, TableKey::new("shop", "my.schema", "order___items").column("data.id"));
+        assert_eq!(key.to_string(), "shop___\"my.schema\"___\"order___items\".\"data.id\"");
+    }
+
+    #[test]
+    fn test_dbml_names_are_unique() {
+        assert_eq!(TableKey::new("shop", "main", "orders").get_dbml_name(), "shop___main___orders");
+        assert_eq!(TableKey::new("shop", "a___b", "c").get_dbml_name(), "shop___a%5F%5F%5Fb___c");
+        assert_eq!(TableKey::new("shop", "a", "b___c").get_dbml_name(), "shop___a___b%5F%5F%5Fc");
+
+        let keys = [
+            TableKey::new("shop", "a___b", "c"),
+            TableKey::new("shop", "a", "b___c"),
+            TableKey::new("shop", "a_", "b"),
+            TableKey::new("shop", "a", "_b"),
+            TableKey::new("shop", "say \"hi\"", "orders"),
+            TableKey::new("shop", "say %22hi%22", "orders"),
+            TableKey::new("orders::main", "my.schema", "order_items"),
+        ];
+        let names: BTreeSet<String> = keys.iter().map(|key| {
+            let name = dbml::format_name(&key.get_dbml_name());
+            let is_valid = match name.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+                Some(quoted) => !quoted.is_empty() && !quoted.contains('"'),
+                None => !name.is_empty() && name.chars().all(|character| character.is_alphanumeric() || character == '_'),
+            };
+            assert!(is_valid, "'{}' is not a valid DBML name", name);
+            name
+        }).collect();
+        assert_eq!(names.len(), keys.len());
+    }
+
+    #[test]
+    fn test_parse_invalid_keys() {
+        for key in ["shop___orders.user_id", "shop___main___orders", "shop___main___\"orders.user_id", "shop______orders.id"] {
+            assert!(ColumnKey::parse(key).is_err(), "{}", key);
+        }
+        assert!(TableKey::parse("shop___orders").is_err());
+    }
+
+}
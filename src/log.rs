@@ -0,0 +1,177 @@
+use std::{io::{self, IsTerminal}, sync::OnceLock, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde_json::{json, Map, Value};
+
+/// The format of the messages written on stderr, given with `--log-format`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// One sentence per line
+    Text,
+    /// One JSON object per line, with the timestamp, level and message (plus the duration of the phases)
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+
+}
+
+/// Writes what the commands are doing on stderr, so stdout only carries their results (DBML files,
+/// search results, validation findings...). The progress bars are drawn above the messages.
+struct Logger {
+    level: Level,
+    format: LogFormat,
+    progress: MultiProgress,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Sets up the logger from the global options: `--quiet` only keeps the warnings and errors, and
+/// `--verbose` adds the details (such as the time taken by each phase)
+pub fn init(quiet: bool, verbose: bool, format: LogFormat) {
+    let level = match (quiet, verbose) {
+        (true, _) => Level::Warn,
+        (_, true) => Level::Debug,
+        _ => Level::Info,
+    };
+
+    // The progress bars are only drawn for someone watching a terminal
+    let draw_target = match level <= Level::Info && format == LogFormat::Text && io::stderr().is_terminal() {
+        true => ProgressDrawTarget::stderr(),
+        false => ProgressDrawTarget::hidden(),
+    };
+
+    let _ = LOGGER.set(Logger { level, format, progress: MultiProgress::with_draw_target(draw_target) });
+}
+
+fn get_logger() -> &'static Logger {
+    LOGGER.get_or_init(|| Logger {
+        level: Level::Info,
+        format: LogFormat::Text,
+        progress: MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+    })
+}
+
+pub fn debug(message: impl Into<String>) {
+    write(Level::Debug, message.into(), Map::new());
+}
+
+pub fn info(message: impl Into<String>) {
+    write(Level::Info, message.into(), Map::new());
+}
+
+pub fn warn(message: impl Into<String>) {
+    write(Level::Warn, message.into(), Map::new());
+}
+
+pub fn error(message: impl Into<String>) {
+    write(Level::Error, message.into(), Map::new());
+}
+
+fn write(level: Level, message: String, fields: Map<String, Value>) {
+    let logger = get_logger();
+    if level < logger.level {
+        return;
+    }
+
+    let line = match logger.format {
+        LogFormat::Text => match level {
+            Level::Warn => format!("Warning: {}", message),
+            Level::Error => format!("Error: {}", message),
+            _ => message,
+        },
+        LogFormat::Json => {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or_default();
+
+            let mut entry = Map::new();
+            entry.insert(String::from("timestamp"), json!(timestamp as u64));
+            entry.insert(String::from("level"), json!(level.as_str()));
+            entry.insert(String::from("message"), json!(message));
+            entry.extend(fields);
+            Value::Object(entry).to_string()
+        }
+    };
+
+    logger.progress.suspend(|| eprintln!("{}", line));
+}
+
+/// A phase of a command, whose duration is logged with `--verbose` once it is finished
+pub struct Phase {
+    name: String,
+    start: Instant,
+}
+
+impl Phase {
+
+    pub fn start(name: impl Into<String>) -> Self {
+        Phase { name: name.into(), start: Instant::now() }
+    }
+
+    pub fn elapsed(&self) -> String {
+        format_duration(self.start.elapsed())
+    }
+
+    pub fn finish(self) {
+        let elapsed = self.start.elapsed();
+
+        let mut fields = Map::new();
+        fields.insert(String::from("phase"), json!(self.name));
+        fields.insert(String::from("elapsed_ms"), json!(elapsed.as_millis() as u64));
+        write(Level::Debug, format!("{} took {}", self.name, format_duration(elapsed)), fields);
+    }
+
+}
+
+/// A spinner counting the rows read by a long query (such as the columns of a scan)
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+
+    pub fn start(message: impl Into<String>) -> Self {
+        let bar = get_logger().progress.add(ProgressBar::new_spinner());
+        bar.set_style(ProgressStyle::with_template("{spinner} {msg}: {human_pos} rows ({elapsed})")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()));
+        bar.set_message(message.into());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Progress { bar }
+    }
+
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Removes the spinner, returning how many rows were counted
+    pub fn finish(self) -> u64 {
+        self.bar.finish_and_clear();
+        self.bar.position()
+    }
+
+}
+
+fn format_duration(duration: Duration) -> String {
+    match duration.as_secs() {
+        0 => format!("{}ms", duration.as_millis()),
+        1..=59 => format!("{:.2}s", duration.as_secs_f64()),
+        seconds => format!("{}m{}s", seconds / 60, seconds % 60),
+    }
+}
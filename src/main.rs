@@ -1,3 +1,5 @@
+use std::process;
+
 use cli::Cli;
 
 mod cli;
@@ -5,11 +7,20 @@ mod commander;
 mod config;
 mod db;
 mod dbml;
+mod error;
+mod history;
+mod key;
+mod log;
+mod output;
+mod schema;
+mod workspace;
+mod yaml;
 
 #[tokio::main]
 async fn main() {
     let cli: Cli = cli::parse();
     if let Err(err) = commander::execute(cli).await {
-        println!("Error: {}", err)
+        log::error(format!("{:#}", err));
+        process::exit(error::get_exit_code(&err));
     }
 }
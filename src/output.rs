@@ -0,0 +1,65 @@
+use std::{path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::log;
+
+/// The format of the results printed on stdout, given with `--format`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Sentences meant to be read
+    Text,
+    /// A single JSON document per command, meant to be consumed by other tools
+    Json,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Sets the format given with `--format`
+pub fn set_format(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn get_format() -> OutputFormat {
+    *FORMAT.get_or_init(|| OutputFormat::Text)
+}
+
+/// The result of a command, printed once it has finished
+pub trait Output: Serialize {
+    fn print_text(&self);
+}
+
+pub fn print(output: &impl Output) -> Result<()> {
+    match get_format() {
+        OutputFormat::Text => output.print_text(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(output)
+            .map_err(|err| anyhow!(format!("Could not format the result as JSON: {}", err)))?),
+    }
+
+    Ok(())
+}
+
+/// Prints how a file would change with `--dry-run`, as a unified diff (`None` when the file does
+/// not exist before or after). With `--format json`, the diff goes to stderr so that stdout keeps
+/// a single JSON document.
+pub fn print_diff(path: &Path, current: Option<&str>, updated: Option<&str>) {
+    if current == updated {
+        log::info(format!("{} would not change", path.display()));
+        return;
+    }
+
+    let path = path.display().to_string();
+    let diff = TextDiff::from_lines(current.unwrap_or_default(), updated.unwrap_or_default())
+        .unified_diff()
+        .context_radius(3)
+        .header(current.map(|_| path.as_str()).unwrap_or("/dev/null"), updated.map(|_| path.as_str()).unwrap_or("/dev/null"))
+        .to_string();
+
+    match get_format() {
+        OutputFormat::Text => print!("{}", diff),
+        OutputFormat::Json => eprint!("{}", diff),
+    }
+}
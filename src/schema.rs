@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use jsonschema::JSONSchema;
+use schemars::schema_for;
+use serde_yaml::Value;
+
+use crate::{config::{FileConfig, ScanStore}, yaml};
+
+/// A part of a project file that does not match the JSON Schema of `FileConfig`
+pub struct SchemaViolation {
+    /// The line of the offending entry in the project file, when it can be found there
+    pub line: Option<usize>,
+    /// The keys leading to the offending value, separated by dots
+    pub path: String,
+    pub message: String,
+}
+
+/// The JSON Schema of the project files
+pub fn get_project_schema() -> serde_json::Value {
+    serde_json::to_value(schema_for!(FileConfig)).unwrap_or_default()
+}
+
+/// The JSON Schema of the scan store files
+pub fn get_store_schema() -> serde_json::Value {
+    serde_json::to_value(schema_for!(ScanStore)).unwrap_or_default()
+}
+
+/// Validates the value a project file (`source`) resolves to against the JSON Schema of the project
+/// files, returning every violation instead of stopping at the first one like deserializing does
+pub fn validate(source: &str, value: &Value) -> Result<Vec<SchemaViolation>> {
+    let schema = get_project_schema();
+    let schema = JSONSchema::compile(&schema)
+        .map_err(|err| anyhow!(format!("Could not compile the JSON Schema of the project files: {}", err)))?;
+    let instance = serde_json::to_value(value)
+        .map_err(|err| anyhow!(format!("Could not convert the config file to JSON: {}", err)))?;
+
+    let mut violations: Vec<SchemaViolation> = match schema.validate(&instance) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| {
+                let path = error.instance_path.clone().into_vec();
+                SchemaViolation {
+                    line: yaml::find_line(source, &path),
+                    path: path.join("."),
+                    message: error.to_string(),
+                }
+            })
+            .collect(),
+    };
+
+    violations.sort_by(|a, b| (a.line, &a.path, &a.message).cmp(&(b.line, &b.path, &b.message)));
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::validate;
+
+    #[test]
+    fn test_validate() {
+        let source = "\
+version: 2
+databases:
+  pay:
+    connection:
+      type: postgres
+      host: localhost
+      port: not_a_port
+      username: test_user
+";
+        let violations = validate(source, &serde_yaml::from_str(source).unwrap()).unwrap();
+
+        let lines: Vec<Option<usize>> = violations.iter().map(|violation| violation.line).collect();
+        assert_eq!(lines, vec![Some(4), Some(5), Some(7)]);
+        assert_eq!(violations[0].path, "databases.pay.connection");
+        assert!(violations[0].message.contains("password"));
+    }
+
+    #[test]
+    fn test_validate_files_without_version_or_with_includes() {
+        let connection = "    connection:\n      type: mysql\n      host: localhost\n      port: 3306\n      username: root\n      password: root\n";
+        let sources = [
+            format!("databases:\n  pay:\n{}", connection),
+            format!("version: 2\ninclude: shared.yaml\ndatabases:\n  pay:\n{}", connection),
+            format!("version: 2\ninclude:\n- a.yaml\n- b.yaml\ndatabases:\n  pay:\n{}", connection),
+        ];
+
+        for source in sources {
+            let violations = validate(&source, &serde_yaml::from_str(&source).unwrap()).unwrap();
+            let messages: Vec<&String> = violations.iter().map(|violation| &violation.message).collect();
+            assert!(violations.is_empty(), "{}: {:?}", source, messages);
+        }
+
+        let source = "version: 2\ninclude: 3\ndatabases: {}\n";
+        assert!(!validate(source, &serde_yaml::from_str(source).unwrap()).unwrap().is_empty());
+    }
+
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+use crate::{config::{self, Config}, error::Error, key::{self, ColumnKey}, yaml};
+
+/// Separates the project from the database name of a key in a workspace: `{project}::{database_name}___{schema_name}___{table_name}.{column_name}`
+pub const PROJECT_SEPARATOR: &str = "::";
+
+/// A workspace file (`{workspace}.workspace.yaml`), which groups projects whose columns reference each other
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceFile {
+    pub projects: Vec<String>,
+    /// References between columns of different projects, with keys prefixed by their project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_references: Option<BTreeMap<String, Vec<String>>>,
+}
+
+pub struct Workspace {
+    pub name: String,
+    pub projects: BTreeMap<String, Config>,
+    pub custom_references: Option<BTreeMap<String, Vec<String>>>,
+    source: String,
+}
+
+impl Workspace {
+
+    /// Merges the projects of the workspace into a single config, whose databases and keys are
+    /// prefixed by their project, so the commands can run across projects as if they were one
+    pub fn to_config(&self) -> Config {
+        let mut databases = BTreeMap::new();
+        let mut references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut custom_references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for (project, config) in &self.projects {
+            for (database_name, database) in &config.databases {
+                databases.insert(qualify(project, database_name), database.clone());
+            }
+            for (key, referenced_keys) in config.references.iter().flatten() {
+                references.insert(qualify_key(project, key), referenced_keys.iter().map(|referenced_key| qualify_key(project, referenced_key)).collect());
+            }
+            for (key, referenced_keys) in config.custom_references.iter().flatten() {
+                custom_references.insert(qualify_key(project, key), referenced_keys.iter().map(|referenced_key| qualify_key(project, referenced_key)).collect());
+            }
+        }
+
+        for (key, referenced_keys) in self.custom_references.clone().map(key::normalize_references).into_iter().flatten() {
+            custom_references.entry(key).or_default().extend(referenced_keys);
+        }
+
+        Config::new(
+            self.name.to_string(),
+            databases,
+            Some(references).filter(|references| !references.is_empty()),
+            Some(custom_references).filter(|custom_references| !custom_references.is_empty()),
+        )
+    }
+
+    /// Saves the custom references of a config made by `to_config`: the ones within a project go
+    /// to its project file and the ones between projects to the workspace file
+    pub fn save(&mut self, config: &Config) -> Result<()> {
+        let mut project_custom_references: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+        let mut custom_references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for (key, referenced_keys) in config.custom_references.iter().flatten() {
+            let (project, unqualified_key) = split(key)
+                .ok_or(Error::InvalidKey(format!("The key '{}' does not start with the project it belongs to", key)))?;
+            if !self.projects.contains_key(&project) {
+                return Err(Error::InvalidKey(format!("The project '{}' is not part of the '{}' workspace", project, self.name)).into());
+            }
+
+            for referenced_key in referenced_keys {
+                match split(referenced_key) {
+                    Some((referenced_project, unqualified_referenced_key)) if referenced_project == project => project_custom_references
+                        .entry(project.to_string())
+                        .or_default()
+                        .entry(unqualified_key.to_string())
+                        .or_default()
+                        .push(unqualified_referenced_key),
+                    _ => custom_references.entry(key.to_string()).or_default().push(referenced_key.to_string()),
+                }
+            }
+        }
+
+        let mut changed_projects: Vec<String> = Vec::new();
+        for (project, project_config) in &mut self.projects {
+            let updated = project_custom_references.remove(project);
+            if project_config.custom_references != updated {
+                project_config.custom_references = updated;
+                changed_projects.push(project.to_string());
+            }
+        }
+        for project in changed_projects {
+            self.projects[&project].save()?;
+        }
+
+        let updated = Some(custom_references).filter(|custom_references| !custom_references.is_empty());
+        if self.custom_references != updated {
+            self.custom_references = updated;
+            self.save_file()?;
+        }
+
+        Ok(())
+    }
+
+    fn save_file(&self) -> Result<()> {
+        let file = WorkspaceFile {
+            projects: self.projects.keys().cloned().collect(),
+            custom_references: self.custom_references.clone(),
+        };
+
+        let original: Value = serde_yaml::from_str(&self.source)
+            .map_err(|err| anyhow!(format!("Could not parse the '{}' workspace file: {}", self.name, err)))?;
+        let mut updated = serde_yaml::to_value(&file)
+            .map_err(|err| anyhow!(format!("Could not generate the updated '{}' workspace file: {}", self.name, err)))?;
+        // Keeps the order in which the projects were listed
+        updated["projects"] = original["projects"].clone();
+
+        let contents = match yaml::update(&self.source, &original, &updated, |contents| serde_yaml::from_str(contents).ok()) {
+            Some(contents) => contents,
+            None => serde_yaml::to_string(&updated)
+                .map_err(|err| anyhow!(format!("Could not generate the updated '{}' workspace file: {}", self.name, err)))?,
+        };
+
+        let path = config::get_workspace_path(&self.name)?;
+        config::write_file(&path, &contents)
+            .map_err(|err| anyhow!(format!("Could not save the '{}' workspace file: {}", self.name, err)))
+    }
+
+}
+
+pub fn load(workspace: &String) -> Result<Workspace> {
+    let (source, file) = read_file(workspace)?;
+
+    let mut projects: BTreeMap<String, Config> = BTreeMap::new();
+    for project in file.projects {
+        if project.contains(PROJECT_SEPARATOR) {
+            return Err(anyhow!("The project name '{}' in the '{}' workspace cannot contain '{}'", project, workspace, PROJECT_SEPARATOR));
+        }
+
+        let config = config::load(&project)
+            .with_context(|| format!("Could not load the project '{}' of the '{}' workspace", project, workspace))?;
+        projects.insert(project, config);
+    }
+
+    Ok(Workspace {
+        name: workspace.to_string(),
+        projects,
+        custom_references: file.custom_references,
+        source,
+    })
+}
+
+/// The projects of a workspace, without loading them
+pub fn get_projects(workspace: &String) -> Result<Vec<String>> {
+    let (_, file) = read_file(workspace)?;
+    Ok(file.projects)
+}
+
+fn read_file(workspace: &String) -> Result<(String, WorkspaceFile)> {
+    let path = config::get_workspace_path(workspace)?;
+    let source = config::read_file(&path)?;
+    let file = serde_yaml::from_str::<WorkspaceFile>(&source)
+        .map_err(|err| Error::Parse(format!("Could not parse the '{}' workspace file: {}", workspace, err)))?;
+
+    Ok((source, file))
+}
+
+/// Prefixes a database name of a project with the project
+pub fn qualify(project: &str, database_name: &str) -> String {
+    format!("{}{}{}", project, PROJECT_SEPARATOR, database_name)
+}
+
+/// Prefixes the database name of a column key of a project with the project (keys that are not valid
+/// are prefixed as they are)
+pub fn qualify_key(project: &str, key: &str) -> String {
+    match ColumnKey::parse(key) {
+        Ok(mut key) => {
+            key.table.database_name = qualify(project, &key.table.database_name);
+            key.to_string()
+        }
+        Err(_) => qualify(project, key),
+    }
+}
+
+/// Splits a column key of a workspace into its project and the key within the project
+pub fn split(key: &str) -> Option<(String, String)> {
+    let mut key = ColumnKey::parse(key).ok()?;
+    let (project, database_name) = key.table.database_name.split_once(PROJECT_SEPARATOR)?;
+    let project = project.to_string();
+    key.table.database_name = database_name.to_string();
+    Some((project, key.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::BTreeMap;
+
+    use crate::config::Config;
+
+    use super::Workspace;
+
+    fn references(entries: &[(&str, &str)]) -> Option<BTreeMap<String, Vec<String>>> {
+        let mut references: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (key, referenced_key) in entries {
+            references.entry(key.to_string()).or_default().push(referenced_key.to_string());
+        }
+        Some(references)
+    }
+
+    #[test]
+    fn test_to_config() {
+        let mut projects = BTreeMap::new();
+        projects.insert(String::from("orders"), Config::new(String::from("orders"), BTreeMap::new(), None, references(&[("main___s___orders.user_id", "main___s___users.id")])));
+        projects.insert(String::from("payments"), Config::new(String::from("payments"), BTreeMap::new(), None, None));
+
+        let workspace = Workspace {
+            name: String::from("shop"),
+            projects,
+            custom_references: references(&[("payments::pay___s___payments.order_id", "orders::main___s___orders.id")]),
+            source: String::new(),
+        };
+
+        let config = workspace.to_config();
+        assert_eq!(config.custom_references, references(&[
+            ("orders::main___s___orders.user_id", "orders::main___s___users.id"),
+            ("payments::pay___s___payments.order_id", "orders::main___s___orders.id"),
+        ]));
+    }
+
+}
@@ -0,0 +1,326 @@
+use serde_yaml::{Mapping, Value};
+
+/// A `key: value` entry of a block mapping, as found in the source text. The entry owns the
+/// comments and blank lines right above its key (`leading`) and the ones after its last line of
+/// content (`trailing`), so a comment describing a key moves and disappears along with it.
+struct Entry {
+    key: String,
+    leading: usize,
+    key_line: usize,
+    content_end: usize,
+    end: usize,
+}
+
+/// Rewrites a YAML document (`source`, which `resolve`s to `original`) so that it resolves to `updated`.
+/// Entries whose value did not change are copied as they are, with their comments, key order and
+/// formatting, so only the sections that were actually modified are re-serialized. Entries of the
+/// source that are in neither value (such as directives handled by `resolve`) are kept too.
+/// Returns `None` for documents that cannot be edited this way (flow style, anchors...).
+pub fn update(source: &str, original: &Value, updated: &Value, resolve: impl Fn(&str) -> Option<Value>) -> Option<String> {
+    let lines: Vec<&str> = source.lines().collect();
+
+    if let (Value::Mapping(original), Value::Mapping(updated)) = (original, updated) {
+        let mut contents = update_mapping(&lines, 0, original, updated)?.join("\n");
+        contents.push('\n');
+
+        if resolve(&contents).as_ref() == Some(&Value::Mapping(updated.clone())) {
+            return Some(contents);
+        }
+    }
+
+    None
+}
+
+fn update_mapping(lines: &[&str], indent: usize, original: &Mapping, updated: &Mapping) -> Option<Vec<String>> {
+    let entries = get_entries(lines, indent)?;
+    let mut output: Vec<String> = Vec::new();
+
+    // Comments before the first key (or a mapping made only of comments)
+    let first_entry_start = entries.first().map(|entry| entry.leading).unwrap_or(lines.len());
+    output.extend(lines[..first_entry_start].iter().map(|line| line.to_string()));
+
+    for entry in &entries {
+        let key = Value::String(entry.key.to_string());
+        let original_value = match (original.get(&key), updated.get(&key)) {
+            (Some(original_value), _) => original_value,
+            (None, None) => {
+                output.extend(lines[entry.leading..entry.end].iter().map(|line| line.to_string()));
+                continue;
+            }
+            (None, Some(_)) => return None,
+        };
+        let trailing = lines[entry.content_end..entry.end].iter().map(|line| line.to_string());
+
+        let updated_value = match updated.get(&key) {
+            Some(updated_value) => updated_value,
+            None => {
+                output.extend(trailing);
+                continue;
+            }
+        };
+
+        if original_value == updated_value {
+            output.extend(lines[entry.leading..entry.end].iter().map(|line| line.to_string()));
+            continue;
+        }
+
+        output.extend(lines[entry.leading..entry.key_line].iter().map(|line| line.to_string()));
+
+        let children = &lines[entry.key_line + 1..entry.content_end];
+        let nested = match (original_value, updated_value, get_block_indent(lines[entry.key_line], children, indent)) {
+            (Value::Mapping(original_value), Value::Mapping(updated_value), Some(child_indent)) =>
+                update_mapping(children, child_indent, original_value, updated_value),
+            _ => None,
+        };
+
+        match nested {
+            Some(nested) => {
+                output.push(lines[entry.key_line].to_string());
+                output.extend(nested);
+            }
+            None => output.extend(serialize_entry(&key, updated_value, indent)?),
+        }
+
+        output.extend(trailing);
+    }
+
+    // Entries that only exist in `original` because `resolve` produced them are left out unless they changed
+    for (key, value) in updated {
+        if !entries.iter().any(|entry| Some(entry.key.as_str()) == key.as_str()) && original.get(key) != Some(value) {
+            output.extend(serialize_entry(key, value, indent)?);
+        }
+    }
+
+    Some(output)
+}
+
+/// Finds the line (1-based) of the entry at `path` (keys of nested block mappings, or indexes of
+/// block sequences) in a YAML document. When the path cannot be followed to its end, because a
+/// value is written in flow style or comes from an include, the line of the deepest entry found is
+/// returned instead. Returns `None` when not even the first key is found.
+pub fn find_line(source: &str, path: &[String]) -> Option<usize> {
+    let lines: Vec<&str> = source.lines().collect();
+    let (mut start, mut end, mut indent) = (0, lines.len(), 0);
+    let mut line: Option<usize> = None;
+
+    for segment in path {
+        let children = &lines[start..end];
+
+        if let Some(entry) = get_entries(children, indent).and_then(|entries| entries.into_iter().find(|entry| &entry.key == segment)) {
+            line = Some(start + entry.key_line);
+            (start, end) = (start + entry.key_line + 1, start + entry.content_end);
+        }
+        else if let Ok(index) = segment.parse::<usize>() {
+            let items: Vec<usize> = children.iter()
+                .enumerate()
+                .filter(|(_, child)| !is_blank_or_comment(child) && get_indent(child) == indent && child.trim_start().starts_with('-'))
+                .map(|(item_line, _)| start + item_line)
+                .collect();
+
+            // Mappings inside sequence items are not followed
+            if let Some(item_line) = items.get(index) {
+                line = Some(*item_line);
+            }
+            break;
+        }
+        else {
+            break;
+        }
+
+        match lines[start..end].iter().find(|child| !is_blank_or_comment(child)) {
+            Some(first_child) => indent = get_indent(first_child),
+            None => break,
+        }
+    }
+
+    line.map(|line| line + 1)
+}
+
+/// Splits the lines of a block mapping indented by `indent` spaces into its entries
+fn get_entries(lines: &[&str], indent: usize) -> Option<Vec<Entry>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut last_content_line: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if is_blank_or_comment(line) {
+            continue;
+        }
+
+        let line_indent = get_indent(line);
+        if line_indent < indent {
+            return None;
+        }
+
+        // A block sequence may be written at the same indentation as the key it belongs to
+        let is_sequence_item = line[line_indent..] == *"-" || line[line_indent..].starts_with("- ");
+        if line_indent == indent && (!is_sequence_item || entries.is_empty()) {
+            let key = parse_key(&line[indent..])?;
+            let leading = match last_content_line {
+                Some(last_content_line) => last_content_line + 1,
+                None => 0,
+            };
+
+            if let Some(previous) = entries.last_mut() {
+                previous.end = leading;
+            }
+
+            entries.push(Entry { key, leading, key_line: index, content_end: index + 1, end: lines.len() });
+        }
+        else if entries.is_empty() {
+            return None;
+        }
+
+        if let Some(current) = entries.last_mut() {
+            current.content_end = index + 1;
+        }
+        last_content_line = Some(index);
+    }
+
+    Some(entries)
+}
+
+/// The indentation of the block mapping nested under a key line, if its value is one
+fn get_block_indent(key_line: &str, children: &[&str], indent: usize) -> Option<usize> {
+    let (_, value) = split_key(&key_line[indent..])?;
+    if !value.is_empty() && !value.starts_with('#') {
+        return None;
+    }
+
+    let first_child = children.iter().find(|line| !is_blank_or_comment(line))?;
+    let child_indent = get_indent(first_child);
+    if child_indent <= indent || first_child.trim_start().starts_with('-') {
+        return None;
+    }
+
+    Some(child_indent)
+}
+
+fn serialize_entry(key: &Value, value: &Value, indent: usize) -> Option<Vec<String>> {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.clone(), value.clone());
+    let contents = serde_yaml::to_string(&mapping).ok()?;
+
+    Some(contents.lines().map(|line| format!("{}{}", " ".repeat(indent), line)).collect())
+}
+
+fn parse_key(text: &str) -> Option<String> {
+    let (key, _) = split_key(text)?;
+    match serde_yaml::from_str::<Value>(key).ok()? {
+        Value::String(key) => Some(key),
+        _ => None,
+    }
+}
+
+/// Splits a `key: value` line (without its indentation) into its raw key and the rest of the line
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    if text.starts_with(['-', '?', '{', '[', '&', '*', '!', '|', '>']) {
+        return None;
+    }
+
+    let key_end = match text.chars().next()? {
+        quote @ ('"' | '\'') => text[1..].find(quote)? + 2,
+        _ => 0,
+    };
+
+    let mut search_from = key_end;
+    loop {
+        let colon = search_from + text[search_from..].find(':')?;
+        let rest = &text[colon + 1..];
+        if rest.is_empty() || rest.starts_with([' ', '\t']) {
+            return Some((text[..colon].trim_end(), rest.trim()));
+        }
+        search_from = colon + 1;
+    }
+}
+
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_yaml::Value;
+
+    use super::{find_line, update};
+
+    const SOURCE: &str = "\
+# Payments project
+databases:
+  # The main database
+  pay:
+    connection:
+      type: mysql
+      host: localhost # overridden in CI
+      port: 3306
+custom_references:
+  pay___a.b:
+  - pay___c.d
+";
+
+    fn edit(change: impl FnOnce(&mut Value)) -> String {
+        let original: Value = serde_yaml::from_str(SOURCE).unwrap();
+        let mut updated = original.clone();
+        change(&mut updated);
+
+        let contents = update(SOURCE, &original, &updated, |contents| serde_yaml::from_str(contents).ok()).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>(&contents).unwrap(), updated);
+        contents
+    }
+
+    #[test]
+    fn test_update_without_changes() {
+        assert_eq!(edit(|_| {}), SOURCE);
+    }
+
+    #[test]
+    fn test_update_keeps_comments_of_unchanged_entries() {
+        let contents = edit(|value| {
+            value["databases"]["pay"]["connection"]["port"] = Value::from(3307);
+        });
+
+        assert!(contents.starts_with("# Payments project\ndatabases:\n  # The main database\n  pay:\n"));
+        assert!(contents.contains("      host: localhost # overridden in CI\n      port: 3307\n"));
+        assert!(contents.ends_with("custom_references:\n  pay___a.b:\n  - pay___c.d\n"));
+    }
+
+    #[test]
+    fn test_update_adds_and_removes_entries() {
+        let contents = edit(|value| {
+            value.as_mapping_mut().unwrap().remove("custom_references");
+            value["databases"]["pay"].as_mapping_mut().unwrap()
+                .insert(Value::from("fingerprints"), serde_yaml::from_str("{ main: '1:2' }").unwrap());
+        });
+
+        assert!(!contents.contains("custom_references"));
+        assert!(contents.contains("      port: 3306\n    fingerprints:\n      main: 1:2\n"));
+    }
+
+    #[test]
+    fn test_update_sequences_written_at_the_key_indent() {
+        // serde_yaml writes the items of a sequence at the indentation of their key
+        let source = "custom_references:\n  # Found by search\n  pay___a.b:\n  - pay___c.d\n  pay___e.f:\n  - pay___c.d\n";
+        let original: Value = serde_yaml::from_str(source).unwrap();
+        let mut updated = original.clone();
+        updated["custom_references"]["pay___e.f"] = serde_yaml::from_str("[pay___g.h]").unwrap();
+
+        let contents = update(source, &original, &updated, |contents| serde_yaml::from_str(contents).ok()).unwrap();
+        assert_eq!(contents, "custom_references:\n  # Found by search\n  pay___a.b:\n  - pay___c.d\n  pay___e.f:\n  - pay___g.h\n");
+    }
+
+    #[test]
+    fn test_find_line() {
+        let path = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(find_line(SOURCE, &path(&["databases", "pay", "connection", "port"])), Some(8));
+        assert_eq!(find_line(SOURCE, &path(&["custom_references", "pay___a.b", "0"])), Some(11));
+        assert_eq!(find_line(SOURCE, &path(&["databases", "pay", "tables"])), Some(4));
+        assert_eq!(find_line(SOURCE, &path(&["include"])), None);
+    }
+
+}